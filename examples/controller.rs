@@ -0,0 +1,150 @@
+//! A long-running controller which keeps a machine ready to brew during business hours
+//!
+//! The machine is turned on at opening time and switched to standby at closing time. In
+//! between, it's left alone, so you can still switch it off by hand. If the water reservoir
+//! runs dry, a warning is logged.
+//!
+//! Tokens are persisted to `~/.lm-controller-tokens.yml` so restarts don't need a fresh
+//! login, and the client logs in again by itself if its refresh token stops working.
+//!
+//! ```bash
+//! export LM_USERNAME="your@email.com"
+//! export LM_PASSWORD="yourpassword"
+//! export LM_SERIAL="MR033274"
+//! # Optional, defaulting to 7 and 18
+//! export LM_OPEN_HOUR=8
+//! export LM_CLOSE_HOUR=17
+//! RUST_LOG=info cargo run --example controller
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{Local, Timelike};
+use lm_rs::{
+    ApiClient, AuthenticationClient, Credentials, FileTokenStore, MachineState,
+    TokenRefreshCallback, TokenStore,
+};
+use log::{info, warn};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often to check the machine
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Persists refreshed tokens so the controller can be restarted without logging in again
+struct PersistTokens {
+    store: FileTokenStore,
+}
+
+impl TokenRefreshCallback for PersistTokens {
+    fn on_tokens_refreshed(&self, credentials: &Credentials) {
+        if let Err(e) = self.store.store(credentials) {
+            warn!("Failed to persist refreshed tokens: {}", e);
+        }
+    }
+}
+
+fn env_hour(name: &str, default: u32) -> Result<u32> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .with_context(|| format!("{} must be an hour between 0 and 23", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Load previously persisted tokens, or log in from scratch
+async fn credentials(
+    username: &str,
+    password: &str,
+    store: &FileTokenStore,
+) -> Result<Credentials> {
+    if let Some(credentials) = store.load(username)? {
+        if credentials.installation_key.is_some() {
+            info!("Using tokens from {}", store.path().display());
+            return Ok(credentials);
+        }
+    }
+
+    let credentials = AuthenticationClient::new()
+        .login_bootstrapped(username, password)
+        .await?;
+    store.store(&credentials)?;
+    info!("Logged in as {}", username);
+
+    Ok(credentials)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let username = env::var("LM_USERNAME").context("LM_USERNAME must be set")?;
+    let password = env::var("LM_PASSWORD").context("LM_PASSWORD must be set")?;
+    let serial = env::var("LM_SERIAL").context("LM_SERIAL must be set")?;
+    let open_hour = env_hour("LM_OPEN_HOUR", 7)?;
+    let close_hour = env_hour("LM_CLOSE_HOUR", 18)?;
+
+    // Not the temporary directory, which other users can often read
+    let token_path = dirs::home_dir()
+        .context("Couldn't find your home directory")?
+        .join(".lm-controller-tokens.yml");
+    let credentials = credentials(
+        &username,
+        &password,
+        &FileTokenStore::new(token_path.clone()),
+    )
+    .await?;
+
+    let callback = Arc::new(PersistTokens {
+        store: FileTokenStore::new(token_path),
+    });
+    let mut api_client = ApiClient::new(credentials, Some(callback))
+        .with_reauthentication(password)
+        .with_idle_warmup(Duration::from_secs(30 * 60));
+
+    let mut warned_about_water = false;
+    let mut was_open = None;
+
+    loop {
+        let hour = Local::now().hour();
+        let is_open = hour >= open_hour && hour < close_hour;
+        // Only act when opening or closing (or on startup), not on every check
+        let changed = was_open != Some(is_open);
+
+        match api_client.get_machine_status(&serial).await {
+            Ok(status) => {
+                if changed {
+                    was_open = Some(is_open);
+                }
+
+                if changed && is_open && !status.is_on() {
+                    info!("Opening time, turning on {}", serial);
+                    if let Err(e) = api_client.turn_on_machine(&serial).await {
+                        warn!("Failed to turn on machine: {}", e);
+                    }
+                } else if changed && !is_open && status.is_on() {
+                    info!("Closing time, switching {} to standby", serial);
+                    if let Err(e) = api_client.turn_off_machine(&serial).await {
+                        warn!("Failed to switch machine to standby: {}", e);
+                    }
+                }
+
+                if status.state() == MachineState::NoWater {
+                    if !warned_about_water {
+                        warn!(
+                            "{} has run out of water - please refill the reservoir",
+                            serial
+                        );
+                        warned_about_water = true;
+                    }
+                } else {
+                    warned_about_water = false;
+                }
+            }
+            Err(e) => warn!("Failed to fetch machine status: {}", e),
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
@@ -0,0 +1,167 @@
+//! Exponential backoff for polling loops, e.g. waiting for a machine to warm up, and for
+//! retrying failed requests
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Work out how long to wait before the next attempt
+///
+/// The delay doubles each time, up to `max`. `jitter` is the fraction (from 0.0 to 1.0) of
+/// the delay which may be randomly taken off it, so that several clients backing off at the
+/// same time don't all retry at once. With a `jitter` of 0.0, the delay is deterministic.
+///
+/// The returned delay never exceeds `max`.
+///
+/// ```
+/// use lm_rs::backoff::next_delay;
+/// use std::time::Duration;
+///
+/// let delay = next_delay(Duration::from_secs(2), Duration::from_secs(30), 0.0);
+/// assert_eq!(delay, Duration::from_secs(4));
+/// ```
+pub fn next_delay(current: Duration, max: Duration, jitter: f64) -> Duration {
+    let delay = std::cmp::min(current.saturating_mul(2), max);
+
+    let jitter = if jitter.is_nan() {
+        0.0
+    } else {
+        jitter.clamp(0.0, 1.0)
+    };
+    if jitter == 0.0 {
+        return delay;
+    }
+
+    delay.mul_f64(1.0 - jitter * random_fraction())
+}
+
+/// How [`ApiClient`](crate::ApiClient) retries GET requests which fail with a server error
+/// (5xx) or a network error
+///
+/// Commands sent to machines are never retried, since the first attempt may have reached the
+/// machine even if the response didn't come back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times to try a request, including the first attempt
+    pub max_attempts: u32,
+    /// How long to wait before the first retry. The delay doubles after each retry, up to
+    /// [`RetryPolicy::MAX_DELAY`].
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// The longest the client waits between two attempts
+    pub const MAX_DELAY: Duration = Duration::from_secs(30);
+
+    /// Make each request once, without retrying
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    /// How long to wait before each retry, in order
+    ///
+    /// ```
+    /// use lm_rs::backoff::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let delays: Vec<Duration> = RetryPolicy::default().delays().collect();
+    /// assert_eq!(delays, [Duration::from_millis(500), Duration::from_secs(1)]);
+    /// ```
+    pub fn delays(&self) -> impl Iterator<Item = Duration> {
+        std::iter::successors(Some(self.base_delay.min(Self::MAX_DELAY)), |delay| {
+            Some(next_delay(*delay, Self::MAX_DELAY, 0.0))
+        })
+        .take(self.max_attempts.saturating_sub(1) as usize)
+    }
+}
+
+/// Three attempts, waiting 500ms before the first retry
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A random number between 0.0 and 1.0, using the randomly-seeded hasher from std rather
+/// than pulling in a random number generator
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff_calculation() {
+        // Test the exponential backoff used when waiting for a machine to be ready
+        let mut delay = Duration::from_secs(2);
+        let max_delay = Duration::from_secs(30);
+
+        // First delay should be 2 seconds
+        assert_eq!(delay, Duration::from_secs(2));
+
+        // Second delay should be 4 seconds
+        delay = next_delay(delay, max_delay, 0.0);
+        assert_eq!(delay, Duration::from_secs(4));
+
+        // Third delay should be 8 seconds
+        delay = next_delay(delay, max_delay, 0.0);
+        assert_eq!(delay, Duration::from_secs(8));
+
+        // Fourth delay should be 16 seconds
+        delay = next_delay(delay, max_delay, 0.0);
+        assert_eq!(delay, Duration::from_secs(16));
+
+        // Fifth delay should be 30 seconds (capped at max)
+        delay = next_delay(delay, max_delay, 0.0);
+        assert_eq!(delay, Duration::from_secs(30));
+
+        // Sixth delay should remain at 30 seconds (still capped)
+        delay = next_delay(delay, max_delay, 0.0);
+        assert_eq!(delay, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_backoff_with_jitter_stays_in_range() {
+        let max_delay = Duration::from_secs(30);
+
+        for _ in 0..100 {
+            let delay = next_delay(Duration::from_secs(8), max_delay, 0.5);
+            assert!(delay >= Duration::from_secs(8));
+            assert!(delay <= Duration::from_secs(16));
+
+            // Out-of-range jitter is clamped, so the delay is never negative or above the max
+            let delay = next_delay(Duration::from_secs(20), max_delay, 5.0);
+            assert!(delay <= max_delay);
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_delays() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(10),
+        };
+        let delays: Vec<Duration> = policy.delays().collect();
+        assert_eq!(
+            delays,
+            [10, 20, 30, 30].map(Duration::from_secs),
+            "delays double up to the maximum"
+        );
+
+        assert_eq!(RetryPolicy::none().delays().count(), 0);
+        let zero_attempts = RetryPolicy {
+            max_attempts: 0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(zero_attempts.delays().count(), 0);
+    }
+}
@@ -3,12 +3,13 @@ use log::{debug, error};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 
 use crate::auth;
+use crate::endpoints::Endpoints;
 use crate::types::{Machine, MachineCommand, MachineStatus, MachinesResponse};
 
 pub struct LaMarzoccoClient {
     client: reqwest::Client,
     access_token: Option<String>,
-    base_url: String,
+    endpoints: Endpoints,
 }
 
 impl Default for LaMarzoccoClient {
@@ -20,16 +21,16 @@ impl Default for LaMarzoccoClient {
 impl LaMarzoccoClient {
     pub fn new() -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: crate::http::default_client(),
             access_token: None,
-            base_url: "https://lion.lamarzocco.io/api/customer-app".to_string(),
+            endpoints: Endpoints::default(),
         }
     }
 
     // Test-specific accessor methods
     #[allow(dead_code)]
     pub fn base_url(&self) -> &str {
-        &self.base_url
+        &self.endpoints.base_url
     }
 
     #[allow(dead_code)]
@@ -41,9 +42,9 @@ impl LaMarzoccoClient {
     #[allow(dead_code)]
     pub fn new_with_base_url(base_url: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: crate::http::default_client(),
             access_token: None,
-            base_url,
+            endpoints: Endpoints::new(base_url),
         }
     }
 
@@ -51,7 +52,8 @@ impl LaMarzoccoClient {
         debug!("Authenticating user: {}", username);
 
         let token =
-            auth::authenticate_with_url(&self.client, &self.base_url, username, password).await?;
+            auth::authenticate_with_url(&self.client, &self.endpoints.base_url, username, password)
+                .await?;
         self.access_token = Some(token);
 
         debug!("Authentication successful");
@@ -77,7 +79,7 @@ impl LaMarzoccoClient {
     pub async fn get_machines(&self) -> Result<Vec<Machine>> {
         debug!("Fetching machines list");
 
-        let url = format!("{}/things", self.base_url);
+        let url = self.endpoints.things();
         let headers = self.get_headers()?;
 
         let response = self.client.get(&url).headers(headers).send().await?;
@@ -119,7 +121,7 @@ impl LaMarzoccoClient {
     pub async fn get_machine_status(&self, serial_number: &str) -> Result<MachineStatus> {
         debug!("Fetching status for machine: {}", serial_number);
 
-        let url = format!("{}/things/{}/dashboard", self.base_url, serial_number);
+        let url = self.endpoints.dashboard(serial_number);
         let headers = self.get_headers()?;
 
         let response = self.client.get(&url).headers(headers).send().await?;
@@ -166,10 +168,9 @@ impl LaMarzoccoClient {
         serial_number: &str,
         command: MachineCommand,
     ) -> Result<()> {
-        let url = format!(
-            "{}/things/{}/command/CoffeeMachineChangeMode",
-            self.base_url, serial_number
-        );
+        let url = self
+            .endpoints
+            .command(serial_number, "CoffeeMachineChangeMode");
         let headers = self.get_headers()?;
 
         debug!("Sending command to {}: {:?}", serial_number, command);
@@ -1,15 +1,21 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use dirs::home_dir;
 use log::{debug, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
+use crate::auth::AuthenticationClient;
+use crate::endpoints::Region;
 use crate::installation_key::InstallationKey;
 use crate::types::Credentials;
 
-/// Configuration data stored in ~/.lm.yml
+/// Configuration data stored in ~/.lm.yml (or ~/.lm.toml or ~/.lm.json)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub username: String,
@@ -21,6 +27,56 @@ pub struct Config {
     /// Version of the CLI that created or last updated this configuration
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+    /// When the tokens were obtained by logging in or refreshing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_obtained_at: Option<DateTime<Utc>>,
+}
+
+impl Config {
+    /// Names of the fields of the config file which this version of the CLI manages
+    const FIELDS: &'static [&'static str] = &[
+        "username",
+        "access_token",
+        "refresh_token",
+        "installation_key",
+        "version",
+        "token_obtained_at",
+    ];
+}
+
+impl Config {
+    /// Make sure the config has an installation key, generating, registering and saving a
+    /// new one if it doesn't (e.g. because it was written by an older version of the CLI)
+    ///
+    /// Returns whether a new key was set up. The new key is only saved once it's registered
+    /// with La Marzocco, so a failure here leaves the config as it was.
+    pub async fn ensure_installation_key(
+        &mut self,
+        auth_client: &AuthenticationClient,
+    ) -> Result<bool> {
+        self.ensure_installation_key_at(&get_config_path()?, auth_client)
+            .await
+    }
+
+    async fn ensure_installation_key_at(
+        &mut self,
+        config_path: &Path,
+        auth_client: &AuthenticationClient,
+    ) -> Result<bool> {
+        if self.installation_key.is_some() {
+            return Ok(false);
+        }
+
+        warn!("No installation key is stored, so setting up a new one");
+        let installation_id = crate::installation_key::generate_installation_id();
+        let installation_key = crate::installation_key::generate_installation_key(installation_id)?;
+        auth_client.register_client(&installation_key).await?;
+
+        // Only the key is written, so tokens kept in the keychain stay out of the file
+        save_installation_key_to(config_path, &installation_key)?;
+        self.installation_key = Some(installation_key);
+        Ok(true)
+    }
 }
 
 impl From<&Credentials> for Config {
@@ -31,6 +87,7 @@ impl From<&Credentials> for Config {
             refresh_token: credentials.refresh_token.clone(),
             installation_key: credentials.installation_key.clone(),
             version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            token_obtained_at: credentials.token_obtained_at,
         }
     }
 }
@@ -42,16 +99,160 @@ impl From<Config> for Credentials {
             access_token: config.access_token,
             refresh_token: config.refresh_token,
             installation_key: config.installation_key,
+            token_obtained_at: config.token_obtained_at,
         }
     }
 }
 
-/// Get the path to the configuration file (~/.lm.yml)
-pub fn get_config_path() -> Result<PathBuf> {
+/// Error shown when the installation key stored in the config file can't be loaded
+pub const CORRUPT_INSTALLATION_KEY: &str =
+    "Your installation key is corrupt; run 'lm login' to regenerate it.";
+
+/// Parse the `installation_key` from a config file, with a helpful error if it's corrupt
+fn parse_installation_key(value: &serde_json::Value) -> Result<InstallationKey> {
+    serde_json::from_value(value.clone()).context(CORRUPT_INSTALLATION_KEY)
+}
+
+/// File format of the configuration file, chosen by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Detect the format from a path's extension, falling back to YAML
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    /// File extension used for config files in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yml",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+
+    fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T> {
+        Ok(match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+            ConfigFormat::Toml => toml::from_str(content)?,
+            ConfigFormat::Json => serde_json::from_str(content)?,
+        })
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<String> {
+        Ok(match self {
+            ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+            ConfigFormat::Toml => toml::to_string(value)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+        })
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            "json" => Ok(ConfigFormat::Json),
+            _ => Err(format!(
+                "Unknown config format '{}'. Expected yaml, toml or json",
+                s
+            )),
+        }
+    }
+}
+
+static CONFIG_FORMAT: OnceLock<ConfigFormat> = OnceLock::new();
+
+/// Use the given format for the configuration file, rather than detecting it
+///
+/// Only the first call has any effect, so this should be called once at startup.
+pub fn set_config_format(format: ConfigFormat) {
+    let _ = CONFIG_FORMAT.set(format);
+}
+
+/// Name of the profile kept at the top level of the configuration file, where a file from
+/// before profiles existed keeps its only set of credentials
+pub const DEFAULT_PROFILE: &str = "default";
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Use the named profile in the configuration file, e.g. for a second La Marzocco account,
+/// rather than the default profile
+///
+/// Only the first call has any effect, so this should be called once at startup.
+pub fn set_profile(name: &str) {
+    let _ = PROFILE.set(name.to_string());
+}
+
+/// The profile set with [`set_profile`], or `None` for the default profile
+pub fn active_profile() -> Option<&'static str> {
+    PROFILE
+        .get()
+        .map(String::as_str)
+        .filter(|name| *name != DEFAULT_PROFILE)
+}
+
+/// Get the fields of a profile from the contents of a config file
+///
+/// The default profile's fields are at the top level. Other profiles are kept by name under
+/// `profiles`.
+fn profile_fields<'a>(
+    map: &'a serde_json::Map<String, serde_json::Value>,
+    profile: Option<&str>,
+) -> Option<&'a serde_json::Map<String, serde_json::Value>> {
+    match profile {
+        None => Some(map),
+        Some(name) => map.get("profiles")?.get(name)?.as_object(),
+    }
+}
+
+/// Get the fields of a profile from the contents of a config file to update them, adding
+/// the profile if it isn't there yet
+fn profile_fields_mut<'a>(
+    map: &'a mut serde_json::Map<String, serde_json::Value>,
+    profile: Option<&str>,
+) -> &'a mut serde_json::Map<String, serde_json::Value> {
+    match profile {
+        None => map,
+        Some(name) => object_entry(object_entry(map, "profiles"), name),
+    }
+}
+
+/// Get the object under `key`, replacing anything else that's there with an empty object
+fn object_entry<'a>(
+    map: &'a mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> &'a mut serde_json::Map<String, serde_json::Value> {
+    let value = map
+        .entry(key)
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    if !value.is_object() {
+        *value = serde_json::Value::Object(serde_json::Map::new());
+    }
+    match value {
+        serde_json::Value::Object(object) => object,
+        _ => unreachable!("The value was just made an object"),
+    }
+}
+
+/// Get the directory holding the configuration file (the home directory, or `LM_HOME`)
+fn get_config_dir() -> Result<PathBuf> {
     if let Some(home_override) = env::var_os("LM_HOME") {
         let candidate = PathBuf::from(home_override);
         if !candidate.as_os_str().is_empty() {
-            return Ok(candidate.join(".lm.yml"));
+            return Ok(candidate);
         }
     }
 
@@ -64,72 +265,389 @@ pub fn get_config_path() -> Result<PathBuf> {
         if let Some(value) = env::var_os(key) {
             let candidate = PathBuf::from(value);
             if !candidate.as_os_str().is_empty() {
-                return Ok(candidate.join(".lm.yml"));
+                return Ok(candidate);
             }
         }
     }
 
-    let home = home_dir().context("Failed to determine home directory")?;
-    Ok(home.join(".lm.yml"))
+    home_dir().context("Failed to determine home directory")
+}
+
+/// Get the path to the configuration file (~/.lm.yml by default)
+///
+/// If a format has been set with [`set_config_format`], the file with that extension is
+/// used. Otherwise, an existing `~/.lm.toml` or `~/.lm.json` is picked up when there's no
+/// `~/.lm.yml`.
+pub fn get_config_path() -> Result<PathBuf> {
+    let dir = get_config_dir()?;
+    let path_for = |format: ConfigFormat| dir.join(format!(".lm.{}", format.extension()));
+
+    if let Some(format) = CONFIG_FORMAT.get() {
+        return Ok(path_for(*format));
+    }
+
+    let yaml_path = path_for(ConfigFormat::Yaml);
+    if !yaml_path.exists() {
+        for format in [ConfigFormat::Toml, ConfigFormat::Json] {
+            let path = path_for(format);
+            if path.exists() {
+                return Ok(path);
+            }
+        }
+    }
+
+    Ok(yaml_path)
 }
 
 /// Load configuration from ~/.lm.yml
 pub fn load_config() -> Result<Config> {
-    let config_path = get_config_path()?;
+    load_config_from(&get_config_path()?)
+}
+
+/// Load configuration from a file, in the format given by its extension
+///
+/// The profile set with [`set_profile`] is loaded, or the default profile if none was set.
+pub fn load_config_from(config_path: &Path) -> Result<Config> {
+    load_profile_from(config_path, active_profile())
+}
 
+fn load_profile_from(config_path: &Path, profile: Option<&str>) -> Result<Config> {
     if !config_path.exists() {
         return Err(anyhow::anyhow!(
             "Configuration file not found. Please run 'lm login' first."
         ));
     }
 
-    let content = fs::read_to_string(&config_path)
+    let content = fs::read_to_string(config_path)
         .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    let incomplete = || anyhow::anyhow!("Configuration incomplete. Please run 'lm login' first.");
+
+    let value = ConfigFormat::from_path(config_path)
+        .parse::<serde_json::Value>(&content)
+        .map_err(|_| incomplete())?;
+    let Some(fields) = value
+        .as_object()
+        .and_then(|map| profile_fields(map, profile))
+    else {
+        let name = profile.unwrap_or(DEFAULT_PROFILE);
+        return Err(anyhow::anyhow!(
+            "Profile '{}' not found. Please run 'lm login --profile {}' first.",
+            name,
+            name
+        ));
+    };
 
     // First attempt to parse as full Config. If required fields are missing, return a clearer error.
-    match serde_yaml::from_str::<Config>(&content) {
+    match serde_json::from_value::<Config>(serde_json::Value::Object(fields.clone())) {
         Ok(config) => {
             debug!("Loaded configuration for user: {}", config.username);
             Ok(config)
         }
         Err(_) => {
+            // A config which is complete apart from a corrupt installation key needs a different fix
+            if let Some(key) = fields.get("installation_key") {
+                parse_installation_key(key)?;
+            }
+
             // If the file exists but isn't a full config (e.g., only installation_key), surface a friendly error
-            Err(anyhow::anyhow!(
-                "Configuration incomplete. Please run 'lm login' first."
-            ))
+            Err(incomplete())
         }
     }
 }
 
 /// Save configuration to ~/.lm.yml
 pub fn save_config(config: &Config) -> Result<()> {
-    let config_path = get_config_path()?;
+    save_config_to(&get_config_path()?, config)
+}
+
+/// Save configuration to a file, in the format given by its extension
+///
+/// The configuration is saved to the profile set with [`set_profile`], or the default
+/// profile. It's merged into the existing file, so other profiles, and any fields this
+/// version of the CLI doesn't know about, are kept.
+pub fn save_config_to(config_path: &Path, config: &Config) -> Result<()> {
+    write_config_to(config_path, config, active_profile(), &[])
+}
+
+/// Write the config file, creating it so that only the current user can read it, since it
+/// may hold tokens
+fn write_config_file(path: &Path, content: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write config file: {}", path.display()))
+}
+
+/// Save configuration to a profile in a file, leaving out the fields in `omit`
+fn write_config_to(
+    config_path: &Path,
+    config: &Config,
+    profile: Option<&str>,
+    omit: &[&str],
+) -> Result<()> {
+    let format = ConfigFormat::from_path(config_path);
+    let mut map = read_config_map(config_path, format)?;
+    let profile_map = profile_fields_mut(&mut map, profile);
+
+    // Fields which aren't set (e.g. no installation key) are removed rather than left stale
+    for field in Config::FIELDS {
+        profile_map.remove(*field);
+    }
+    match serde_json::to_value(config).context("Failed to serialize configuration")? {
+        serde_json::Value::Object(fields) => profile_map.extend(
+            fields
+                .into_iter()
+                .filter(|(field, _)| !omit.contains(&field.as_str())),
+        ),
+        _ => unreachable!("Config always serializes to a map"),
+    }
 
-    let content = serde_yaml::to_string(config).context("Failed to serialize configuration")?;
+    let content = format
+        .serialize(&map)
+        .context("Failed to serialize configuration")?;
 
-    fs::write(&config_path, content)
-        .with_context(|| format!("Failed to write config file: {}", config_path.display()))?;
+    write_config_file(config_path, &content)?;
 
     debug!("Saved configuration for user: {}", config.username);
     Ok(())
 }
 
+/// Service name the access and refresh tokens are stored under in the OS keychain
+pub const KEYCHAIN_SERVICE: &str = "lm-cli";
+
+/// Fields of the config which are kept in the keychain, when there is one
+const KEYCHAIN_FIELDS: &[&str] = &["access_token", "refresh_token"];
+
+/// Field of the config file holding the region the profile logged in to
+///
+/// It isn't part of [`Config`], so saving refreshed tokens leaves it alone.
+const REGION_FIELD: &str = "region";
+
+/// Somewhere to keep secrets outside the config file, keyed by username
+trait Keychain {
+    fn get(&self, username: &str, name: &str) -> Result<Option<String>>;
+    fn set(&self, username: &str, name: &str, secret: &str) -> Result<()>;
+    fn delete(&self, username: &str, name: &str) -> Result<()>;
+}
+
+/// The OS keychain (e.g. macOS Keychain, Windows Credential Manager or the Secret Service on
+/// Linux), through the `keyring` crate
+#[cfg(feature = "keychain")]
+struct OsKeychain;
+
+#[cfg(feature = "keychain")]
+impl OsKeychain {
+    fn entry(username: &str, name: &str) -> Result<keyring::Entry> {
+        Ok(keyring::Entry::new(
+            KEYCHAIN_SERVICE,
+            &format!("{}:{}", username, name),
+        )?)
+    }
+}
+
+#[cfg(feature = "keychain")]
+impl Keychain for OsKeychain {
+    fn get(&self, username: &str, name: &str) -> Result<Option<String>> {
+        match Self::entry(username, name)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, username: &str, name: &str, secret: &str) -> Result<()> {
+        Ok(Self::entry(username, name)?.set_password(secret)?)
+    }
+
+    fn delete(&self, username: &str, name: &str) -> Result<()> {
+        match Self::entry(username, name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// The OS keychain, if the CLI was built with the `keychain` feature
+fn os_keychain() -> Option<&'static dyn Keychain> {
+    #[cfg(feature = "keychain")]
+    return Some(&OsKeychain);
+    #[cfg(not(feature = "keychain"))]
+    None
+}
+
+/// Load configuration from ~/.lm.yml, with the access and refresh tokens from the OS keychain
+/// if they were saved there by [`save_config_secure`]
+///
+/// Config files which have their own tokens, e.g. because they were saved without a
+/// keychain, are loaded like [`load_config`].
+pub fn load_config_secure() -> Result<Config> {
+    load_config_secure_from(&get_config_path()?, active_profile(), os_keychain())
+}
+
+fn load_config_secure_from(
+    config_path: &Path,
+    profile: Option<&str>,
+    keychain: Option<&dyn Keychain>,
+) -> Result<Config> {
+    if let Some(keychain) = keychain {
+        if let Some(config) = load_config_with_keychain(config_path, profile, keychain)? {
+            return Ok(config);
+        }
+    }
+
+    load_profile_from(config_path, profile)
+}
+
+/// Load a config file which doesn't have its own tokens, taking them from the keychain
+///
+/// Returns `None` if the file has its own tokens or the keychain doesn't have any, so the
+/// file can be loaded as usual.
+fn load_config_with_keychain(
+    config_path: &Path,
+    profile: Option<&str>,
+    keychain: &dyn Keychain,
+) -> Result<Option<Config>> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    let Ok(serde_json::Value::Object(file)) =
+        ConfigFormat::from_path(config_path).parse::<serde_json::Value>(&content)
+    else {
+        return Ok(None);
+    };
+    let Some(mut map) = profile_fields(&file, profile).cloned() else {
+        return Ok(None);
+    };
+    if map.contains_key("access_token") {
+        return Ok(None);
+    }
+    let Some(username) = map.get("username").and_then(|value| value.as_str()) else {
+        return Ok(None);
+    };
+    let username = username.to_string();
+
+    for field in KEYCHAIN_FIELDS {
+        match keychain
+            .get(&username, field)
+            .context("Failed to read tokens from the keychain")?
+        {
+            Some(secret) => {
+                map.insert(field.to_string(), serde_json::Value::String(secret));
+            }
+            None => return Ok(None),
+        }
+    }
+
+    debug!("Loaded tokens for user {} from the keychain", username);
+    Ok(serde_json::from_value(serde_json::Value::Object(map)).ok())
+}
+
+/// Save configuration to ~/.lm.yml, keeping the access and refresh tokens in the OS keychain
+/// rather than the file
+///
+/// Falls back to [`save_config`] if there's no keychain, e.g. because the CLI was built
+/// without the `keychain` feature or no keychain service is running. Returns whether the
+/// tokens went into the keychain.
+pub fn save_config_secure(config: &Config) -> Result<bool> {
+    save_config_secure_to(&get_config_path()?, config, active_profile(), os_keychain())
+}
+
+fn save_config_secure_to(
+    config_path: &Path,
+    config: &Config,
+    profile: Option<&str>,
+    keychain: Option<&dyn Keychain>,
+) -> Result<bool> {
+    let Some(keychain) = keychain else {
+        write_config_to(config_path, config, profile, &[])?;
+        return Ok(false);
+    };
+
+    let stored = keychain
+        .set(&config.username, "access_token", &config.access_token)
+        .and_then(|()| keychain.set(&config.username, "refresh_token", &config.refresh_token));
+    if let Err(e) = stored {
+        warn!(
+            "Failed to store tokens in the keychain, so saving them to the config file: {}",
+            e
+        );
+        write_config_to(config_path, config, profile, &[])?;
+        return Ok(false);
+    }
+
+    write_config_to(config_path, config, profile, KEYCHAIN_FIELDS)?;
+    debug!("Saved tokens for user {} to the keychain", config.username);
+    Ok(true)
+}
+
+/// Read the fields of an existing config file, so they can be updated without losing any
+///
+/// A missing or empty file is treated as having no fields. A file which can't be read or
+/// parsed is an error rather than being replaced, so a typo doesn't wipe out tokens, other
+/// profiles or fields this version doesn't know about.
+fn read_config_map(
+    path: &Path,
+    format: ConfigFormat,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    if !path.exists() {
+        return Ok(serde_json::Map::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let value = format
+        .parse::<serde_json::Value>(&content)
+        .with_context(|| {
+            format!(
+                "Failed to parse config file {}. Please fix or delete it.",
+                path.display()
+            )
+        })?;
+    match value {
+        serde_json::Value::Object(map) => Ok(map),
+        serde_json::Value::Null => Ok(serde_json::Map::new()),
+        _ => Err(anyhow::anyhow!(
+            "Config file {} isn't a map of settings. Please fix or delete it.",
+            path.display()
+        )),
+    }
+}
+
 /// Load only the installation key from the main config file if present
 pub fn load_installation_key_partial() -> Result<InstallationKey> {
-    let path = get_config_path()?;
+    load_installation_key_from(&get_config_path()?)
+}
+
+fn load_installation_key_from(path: &Path) -> Result<InstallationKey> {
     if !path.exists() {
         return Err(anyhow::anyhow!("Installation key not found"));
     }
 
-    let content = fs::read_to_string(&path)
+    let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read config file: {}", path.display()))?;
 
-    let mut value: serde_yaml::Value = serde_yaml::from_str(&content)
+    let value: serde_json::Value = ConfigFormat::from_path(path)
+        .parse(&content)
         .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
 
-    if let Some(install_val) = value.get_mut("installation_key") {
-        let key: InstallationKey = serde_yaml::from_value(install_val.clone())
-            .context("Failed to parse installation_key from config")?;
+    let fields = value
+        .as_object()
+        .and_then(|map| profile_fields(map, active_profile()));
+    if let Some(install_val) = fields.and_then(|fields| fields.get("installation_key")) {
+        let key = parse_installation_key(install_val)?;
         debug!(
             "Loaded installation key from main config: {}",
             key.installation_id
@@ -142,45 +660,25 @@ pub fn load_installation_key_partial() -> Result<InstallationKey> {
 
 /// Save/update only the installation key inside the main config file
 pub fn save_installation_key_partial(key: &InstallationKey) -> Result<()> {
-    let path = get_config_path()?;
-    let mut root = if path.exists() {
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-        serde_yaml::from_str::<serde_yaml::Value>(&content)
-            .with_context(|| format!("Failed to parse config file: {}", path.display()))?
-    } else {
-        serde_yaml::Value::Mapping(serde_yaml::Mapping::new())
-    };
+    save_installation_key_to(&get_config_path()?, key)
+}
 
-    let key_val = serde_yaml::to_value(key).context("Failed to serialize installation key")?;
-    let version_val = serde_yaml::Value::String(env!("CARGO_PKG_VERSION").to_string());
+fn save_installation_key_to(path: &Path, key: &InstallationKey) -> Result<()> {
+    let format = ConfigFormat::from_path(path);
+    let mut map = read_config_map(path, format)?;
+    let profile_map = profile_fields_mut(&mut map, active_profile());
 
-    if let serde_yaml::Value::Mapping(ref mut map) = root {
-        map.insert(
-            serde_yaml::Value::String("installation_key".to_string()),
-            key_val,
-        );
-        map.insert(
-            serde_yaml::Value::String("version".to_string()),
-            version_val,
-        );
-    } else {
-        // If the root isn't a mapping, replace it with a mapping
-        let mut map = serde_yaml::Mapping::new();
-        map.insert(
-            serde_yaml::Value::String("installation_key".to_string()),
-            key_val,
-        );
-        map.insert(
-            serde_yaml::Value::String("version".to_string()),
-            version_val,
-        );
-        root = serde_yaml::Value::Mapping(map);
-    }
+    let key_val = serde_json::to_value(key).context("Failed to serialize installation key")?;
+    profile_map.insert("installation_key".to_string(), key_val);
+    profile_map.insert(
+        "version".to_string(),
+        serde_json::Value::String(env!("CARGO_PKG_VERSION").to_string()),
+    );
 
-    let content = serde_yaml::to_string(&root).context("Failed to serialize YAML")?;
-    fs::write(&path, content)
-        .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    let content = format
+        .serialize(&map)
+        .context("Failed to serialize configuration")?;
+    write_config_file(path, &content)?;
     debug!(
         "Saved installation key to main config: {}",
         key.installation_id
@@ -188,14 +686,59 @@ pub fn save_installation_key_partial(key: &InstallationKey) -> Result<()> {
     Ok(())
 }
 
-/// Clear the configuration file (logout)
+/// Load the region saved for the current profile by [`save_region`], if there is one
+pub fn load_region() -> Result<Option<Region>> {
+    load_region_from(&get_config_path()?)
+}
+
+fn load_region_from(path: &Path) -> Result<Option<Region>> {
+    let map = read_config_map(path, ConfigFormat::from_path(path))?;
+    let Some(region) = profile_fields(&map, active_profile())
+        .and_then(|fields| fields.get(REGION_FIELD))
+        .and_then(|region| region.as_str())
+    else {
+        return Ok(None);
+    };
+    region
+        .parse()
+        .map(Some)
+        .map_err(|e: String| anyhow::anyhow!("Invalid region in {}: {}", path.display(), e))
+}
+
+/// Save the region the current profile logged in to, so later commands use the same API host
+pub fn save_region(region: Region) -> Result<()> {
+    save_region_to(&get_config_path()?, region)
+}
+
+fn save_region_to(path: &Path, region: Region) -> Result<()> {
+    let format = ConfigFormat::from_path(path);
+    let mut map = read_config_map(path, format)?;
+    profile_fields_mut(&mut map, active_profile()).insert(
+        REGION_FIELD.to_string(),
+        serde_json::Value::String(region.to_string()),
+    );
+
+    let content = format
+        .serialize(&map)
+        .context("Failed to serialize configuration")?;
+    write_config_file(path, &content)?;
+    debug!("Saved region {} to config", region);
+    Ok(())
+}
+
+/// Clear the current profile from the configuration file (logout), along with any tokens
+/// stored in the OS keychain
+///
+/// Other profiles are kept. Once there are none left, the file is removed.
 pub fn clear_config() -> Result<()> {
     let config_path = get_config_path()?;
 
+    if let Some(keychain) = os_keychain() {
+        clear_keychain(&config_path, active_profile(), keychain);
+    }
+
     if config_path.exists() {
-        fs::remove_file(&config_path)
-            .with_context(|| format!("Failed to remove config file: {}", config_path.display()))?;
-        debug!("Configuration file cleared");
+        clear_profile_at(&config_path, active_profile())?;
     } else {
         warn!("Configuration file does not exist, nothing to clear");
     }
@@ -203,6 +746,71 @@ pub fn clear_config() -> Result<()> {
     Ok(())
 }
 
+/// Remove a profile from a config file, removing the whole file if no profiles are left
+fn clear_profile_at(config_path: &Path, profile: Option<&str>) -> Result<()> {
+    let format = ConfigFormat::from_path(config_path);
+    let mut map = read_config_map(config_path, format)?;
+
+    match profile {
+        None => {
+            for field in Config::FIELDS {
+                map.remove(*field);
+            }
+            map.remove(REGION_FIELD);
+        }
+        Some(name) => {
+            if let Some(serde_json::Value::Object(profiles)) = map.get_mut("profiles") {
+                profiles.remove(name);
+                if profiles.is_empty() {
+                    map.remove("profiles");
+                }
+            }
+        }
+    }
+
+    let profiles_left =
+        map.contains_key("profiles") || Config::FIELDS.iter().any(|field| map.contains_key(*field));
+    if profiles_left {
+        let content = format
+            .serialize(&map)
+            .context("Failed to serialize configuration")?;
+        write_config_file(config_path, &content)?;
+        debug!("Profile {} cleared", profile.unwrap_or(DEFAULT_PROFILE));
+    } else {
+        fs::remove_file(config_path)
+            .with_context(|| format!("Failed to remove config file: {}", config_path.display()))?;
+        debug!("Configuration file cleared");
+    }
+
+    Ok(())
+}
+
+/// Remove the tokens of the user in a profile of a config file from the keychain
+///
+/// Failures are only logged, so the config file is still cleared.
+fn clear_keychain(config_path: &Path, profile: Option<&str>, keychain: &dyn Keychain) {
+    let format = ConfigFormat::from_path(config_path);
+    let map = match read_config_map(config_path, format) {
+        Ok(map) => map,
+        Err(e) => {
+            warn!("Failed to find the user whose tokens to clear: {:#}", e);
+            return;
+        }
+    };
+    let Some(username) = profile_fields(&map, profile)
+        .and_then(|fields| fields.get("username"))
+        .and_then(|value| value.as_str())
+    else {
+        return;
+    };
+
+    for field in KEYCHAIN_FIELDS {
+        if let Err(e) = keychain.delete(username, field) {
+            warn!("Failed to remove {} from the keychain: {}", field, e);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,6 +822,7 @@ mod tests {
             access_token: "access123".to_string(),
             refresh_token: "refresh456".to_string(),
             installation_key: None,
+            token_obtained_at: None,
         };
 
         let config = Config::from(&credentials);
@@ -236,6 +845,7 @@ mod tests {
             refresh_token: "refresh456".to_string(),
             installation_key: None,
             version: Some("0.2.1".to_string()),
+            token_obtained_at: None,
         };
 
         let yaml = serde_yaml::to_string(&config).unwrap();
@@ -276,6 +886,7 @@ refresh_token: refresh456
             access_token: "access123".to_string(),
             refresh_token: "refresh456".to_string(),
             installation_key: None,
+            token_obtained_at: None,
         };
 
         let config = Config::from(&credentials);
@@ -298,4 +909,461 @@ refresh_token: refresh456
         assert!(content.contains("version:"));
         assert!(content.contains(env!("CARGO_PKG_VERSION")));
     }
+
+    /// A keychain held in memory, which can be made to fail like a locked keychain
+    #[derive(Default)]
+    struct MemoryKeychain {
+        secrets: std::cell::RefCell<std::collections::HashMap<String, String>>,
+        broken: bool,
+    }
+
+    impl Keychain for MemoryKeychain {
+        fn get(&self, username: &str, name: &str) -> Result<Option<String>> {
+            Ok(self
+                .secrets
+                .borrow()
+                .get(&format!("{}:{}", username, name))
+                .cloned())
+        }
+
+        fn set(&self, username: &str, name: &str, secret: &str) -> Result<()> {
+            if self.broken {
+                return Err(anyhow::anyhow!("Keychain is locked"));
+            }
+            self.secrets
+                .borrow_mut()
+                .insert(format!("{}:{}", username, name), secret.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, username: &str, name: &str) -> Result<()> {
+            self.secrets
+                .borrow_mut()
+                .remove(&format!("{}:{}", username, name));
+            Ok(())
+        }
+    }
+
+    fn test_config_with_installation_key() -> Config {
+        Config {
+            username: "test@example.com".to_string(),
+            access_token: "access123".to_string(),
+            refresh_token: "refresh456".to_string(),
+            installation_key: Some(
+                crate::installation_key::generate_installation_key(
+                    "test-installation-id".to_string(),
+                )
+                .unwrap(),
+            ),
+            version: Some("0.2.1".to_string()),
+            token_obtained_at: None,
+        }
+    }
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/me/.lm.yml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/me/.lm.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/me/.lm.JSON")),
+            ConfigFormat::Json
+        );
+        // Anything else is treated as YAML, as before
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/me/.lm")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!("yaml".parse::<ConfigFormat>(), Ok(ConfigFormat::Yaml));
+        assert!("ini".parse::<ConfigFormat>().is_err());
+    }
+
+    #[test]
+    fn test_config_round_trip_in_each_format() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = test_config_with_installation_key();
+        config.token_obtained_at = Some(Utc::now());
+        let key = config.installation_key.as_ref().unwrap();
+
+        for format in [ConfigFormat::Yaml, ConfigFormat::Toml, ConfigFormat::Json] {
+            let path = temp_dir.path().join(format!(".lm.{}", format.extension()));
+            save_config_to(&path, &config).unwrap();
+
+            // The file really is in the requested format
+            let content = fs::read_to_string(&path).unwrap();
+            match format {
+                ConfigFormat::Yaml => assert!(content.contains("username: test@example.com")),
+                ConfigFormat::Toml => assert!(content.contains("username = \"test@example.com\"")),
+                ConfigFormat::Json => {
+                    assert!(content.contains("\"username\": \"test@example.com\""))
+                }
+            }
+
+            let loaded = load_config_from(&path).unwrap();
+            assert_eq!(loaded.username, config.username);
+            assert_eq!(loaded.access_token, config.access_token);
+            assert_eq!(loaded.refresh_token, config.refresh_token);
+            assert_eq!(loaded.version, config.version);
+            assert_eq!(loaded.token_obtained_at, config.token_obtained_at);
+            let loaded_key = loaded.installation_key.unwrap();
+            assert_eq!(loaded_key.installation_id, key.installation_id);
+            assert_eq!(loaded_key.secret, key.secret);
+            assert_eq!(loaded_key.public_key_b64(), key.public_key_b64());
+        }
+    }
+
+    #[test]
+    fn test_save_config_secure_keeps_tokens_in_keychain() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let config = test_config_with_installation_key();
+        let keychain = MemoryKeychain::default();
+
+        // A config saved before the keychain was used has its tokens moved out of the file
+        save_config_to(&path, &config).unwrap();
+        assert!(save_config_secure_to(&path, &config, None, Some(&keychain)).unwrap());
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("username: test@example.com"));
+        assert!(content.contains("installation_key"));
+        assert!(!content.contains("access123"));
+        assert!(!content.contains("refresh456"));
+        assert_eq!(
+            keychain.get("test@example.com", "access_token").unwrap(),
+            Some("access123".to_string())
+        );
+
+        let loaded = load_config_secure_from(&path, None, Some(&keychain)).unwrap();
+        assert_eq!(loaded.access_token, "access123");
+        assert_eq!(loaded.refresh_token, "refresh456");
+        assert!(loaded.installation_key.is_some());
+
+        // Without the tokens, the config file alone isn't enough
+        assert!(load_config_secure_from(&path, None, None).is_err());
+
+        clear_keychain(&path, None, &keychain);
+        assert!(keychain.secrets.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_save_config_secure_falls_back_to_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let config = test_config_with_installation_key();
+
+        assert!(!save_config_secure_to(&path, &config, None, None).unwrap());
+
+        let keychain = MemoryKeychain {
+            broken: true,
+            ..MemoryKeychain::default()
+        };
+        assert!(!save_config_secure_to(&path, &config, None, Some(&keychain)).unwrap());
+
+        // The tokens are in the file, so it loads with or without a keychain
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("access123"));
+        for keychain in [None, Some(&keychain as &dyn Keychain)] {
+            let loaded = load_config_secure_from(&path, None, keychain).unwrap();
+            assert_eq!(loaded.access_token, "access123");
+            assert_eq!(loaded.refresh_token, "refresh456");
+        }
+
+        // Only the current user can read the tokens
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ensure_installation_key() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/init"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let auth_client = AuthenticationClient::new_with_base_url(mock_server.uri());
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let mut config = test_config_with_installation_key();
+        config.installation_key = None;
+        save_config_to(&path, &config).unwrap();
+
+        assert!(config
+            .ensure_installation_key_at(&path, &auth_client)
+            .await
+            .unwrap());
+        let key = config.installation_key.clone().unwrap();
+
+        // The new key is saved, and kept from then on without registering again
+        let mut loaded = load_config_from(&path).unwrap();
+        assert_eq!(
+            loaded.installation_key.as_ref().unwrap().installation_id,
+            key.installation_id
+        );
+        assert!(!loaded
+            .ensure_installation_key_at(&path, &auth_client)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_ensure_installation_key_leaves_config_alone_if_registration_fails() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/init"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let auth_client = AuthenticationClient::new_with_base_url(mock_server.uri());
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let mut config = test_config_with_installation_key();
+        config.installation_key = None;
+        save_config_to(&path, &config).unwrap();
+
+        assert!(config
+            .ensure_installation_key_at(&path, &auth_client)
+            .await
+            .is_err());
+        assert!(config.installation_key.is_none());
+        assert!(load_config_from(&path).unwrap().installation_key.is_none());
+    }
+
+    #[test]
+    fn test_installation_key_partial_round_trip_in_each_format() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let key = test_config_with_installation_key()
+            .installation_key
+            .unwrap();
+
+        for format in [ConfigFormat::Yaml, ConfigFormat::Toml, ConfigFormat::Json] {
+            let path = temp_dir.path().join(format!(".lm.{}", format.extension()));
+
+            // Saving the key alone leaves an incomplete config
+            save_installation_key_to(&path, &key).unwrap();
+            let loaded = load_installation_key_from(&path).unwrap();
+            assert_eq!(loaded.installation_id, key.installation_id);
+            assert!(load_config_from(&path).is_err());
+
+            // Saving the key again keeps the rest of an existing config
+            let mut config = test_config_with_installation_key();
+            config.installation_key = None;
+            save_config_to(&path, &config).unwrap();
+            save_installation_key_to(&path, &key).unwrap();
+
+            let loaded = load_config_from(&path).unwrap();
+            assert_eq!(loaded.username, "test@example.com");
+            assert_eq!(loaded.version, Some(env!("CARGO_PKG_VERSION").to_string()));
+            assert_eq!(
+                loaded.installation_key.unwrap().installation_id,
+                key.installation_id
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupt_installation_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let config = test_config_with_installation_key();
+        let key = config.installation_key.clone().unwrap();
+
+        let corruptions = [
+            // Not base64 at all
+            ("secret", "not base64!"),
+            // Valid base64, but the wrong length
+            ("secret", "c2hvcnQ="),
+            ("private_key", "c2hvcnQ="),
+        ];
+
+        for (field, corrupt_value) in corruptions {
+            let mut value = serde_json::to_value(&config).unwrap();
+            value["installation_key"][field] = corrupt_value.into();
+            fs::write(&path, ConfigFormat::Yaml.serialize(&value).unwrap()).unwrap();
+
+            let error = load_config_from(&path).unwrap_err();
+            assert_eq!(error.to_string(), CORRUPT_INSTALLATION_KEY);
+            // The underlying problem is kept for verbose output
+            assert!(format!("{:#}", error).contains(&field.replace('_', " ")));
+
+            let error = load_installation_key_from(&path).unwrap_err();
+            assert_eq!(error.to_string(), CORRUPT_INSTALLATION_KEY);
+
+            // Saving a fresh key replaces the corrupt one
+            save_installation_key_to(&path, &key).unwrap();
+            assert!(load_config_from(&path).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_save_config_keeps_unknown_fields() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = test_config_with_installation_key();
+
+        let existing = [
+            (
+                ConfigFormat::Yaml,
+                "username: old@example.com\naccess_token: old\nrefresh_token: old\nfavourite_drink: cortado\nversion: 0.1.0\n",
+            ),
+            (
+                ConfigFormat::Toml,
+                "username = \"old@example.com\"\naccess_token = \"old\"\nrefresh_token = \"old\"\nfavourite_drink = \"cortado\"\nversion = \"0.1.0\"\n",
+            ),
+            (
+                ConfigFormat::Json,
+                "{\"username\": \"old@example.com\", \"access_token\": \"old\", \"refresh_token\": \"old\", \"favourite_drink\": \"cortado\", \"version\": \"0.1.0\"}",
+            ),
+        ];
+
+        for (format, content) in existing {
+            let path = temp_dir.path().join(format!(".lm.{}", format.extension()));
+            fs::write(&path, content).unwrap();
+
+            save_config_to(&path, &config).unwrap();
+
+            let content = fs::read_to_string(&path).unwrap();
+            let value: serde_json::Value = format.parse(&content).unwrap();
+            assert_eq!(value["favourite_drink"], "cortado");
+            assert_eq!(value["username"], "test@example.com");
+            assert_eq!(value["version"], "0.2.1");
+            assert!(load_config_from(&path).is_ok());
+
+            // Known fields which are no longer set are removed, rather than left stale
+            let mut without_key = config.clone();
+            without_key.installation_key = None;
+            save_config_to(&path, &without_key).unwrap();
+            let loaded = load_config_from(&path).unwrap();
+            assert!(loaded.installation_key.is_none());
+            let content = fs::read_to_string(&path).unwrap();
+            assert!(content.contains("cortado"));
+        }
+    }
+
+    #[test]
+    fn test_unparseable_config_is_not_replaced() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let content = "username: test@example.com\naccess_token: [unclosed\n";
+        fs::write(&path, content).unwrap();
+
+        let error = save_config_to(&path, &test_config_with_installation_key()).unwrap_err();
+        assert!(error.to_string().contains("Failed to parse config file"));
+        let key = crate::installation_key::generate_installation_key(
+            crate::installation_key::generate_installation_id(),
+        )
+        .unwrap();
+        assert!(save_installation_key_to(&path, &key).is_err());
+        assert!(clear_profile_at(&path, None).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        // An empty file has nothing to lose
+        fs::write(&path, "").unwrap();
+        assert!(save_config_to(&path, &test_config_with_installation_key()).is_ok());
+    }
+
+    #[test]
+    fn test_region_is_kept_when_tokens_are_saved() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        assert_eq!(load_region_from(&path).unwrap(), None);
+
+        save_config_to(&path, &test_config_with_installation_key()).unwrap();
+        save_region_to(&path, Region::Us).unwrap();
+        // Saving refreshed tokens doesn't forget the region
+        save_config_to(&path, &test_config_with_installation_key()).unwrap();
+        assert_eq!(load_region_from(&path).unwrap(), Some(Region::Us));
+
+        clear_profile_at(&path, None).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_profiles_in_each_format() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = test_config_with_installation_key();
+        let mut office = config.clone();
+        office.username = "office@example.com".to_string();
+        office.installation_key = None;
+
+        for format in [ConfigFormat::Yaml, ConfigFormat::Toml, ConfigFormat::Json] {
+            let path = temp_dir.path().join(format!(".lm.{}", format.extension()));
+            save_config_to(&path, &config).unwrap();
+            write_config_to(&path, &office, Some("office"), &[]).unwrap();
+
+            // The default profile is the one at the top level, so older files still load
+            assert_eq!(
+                load_profile_from(&path, None).unwrap().username,
+                "test@example.com"
+            );
+            assert_eq!(
+                load_profile_from(&path, Some("office")).unwrap().username,
+                "office@example.com"
+            );
+            let err = load_profile_from(&path, Some("home")).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("Profile 'home' not found. Please run 'lm login --profile home' first."));
+
+            // Logging out of one profile leaves the other alone
+            clear_profile_at(&path, Some("office")).unwrap();
+            assert!(load_profile_from(&path, Some("office")).is_err());
+            assert!(load_profile_from(&path, None).is_ok());
+
+            write_config_to(&path, &office, Some("office"), &[]).unwrap();
+            clear_profile_at(&path, None).unwrap();
+            assert!(load_profile_from(&path, None).is_err());
+            assert!(load_profile_from(&path, Some("office")).is_ok());
+
+            // The file goes once the last profile is cleared
+            clear_profile_at(&path, Some("office")).unwrap();
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn test_profiles_keep_tokens_in_keychain_separately() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(".lm.yml");
+        let keychain = MemoryKeychain::default();
+        let config = test_config_with_installation_key();
+        let mut office = config.clone();
+        office.username = "office@example.com".to_string();
+        office.access_token = "office-access".to_string();
+
+        save_config_secure_to(&path, &config, None, Some(&keychain)).unwrap();
+        save_config_secure_to(&path, &office, Some("office"), Some(&keychain)).unwrap();
+
+        let loaded = load_config_secure_from(&path, Some("office"), Some(&keychain)).unwrap();
+        assert_eq!(loaded.access_token, "office-access");
+        let loaded = load_config_secure_from(&path, None, Some(&keychain)).unwrap();
+        assert_eq!(loaded.access_token, "access123");
+
+        clear_keychain(&path, Some("office"), &keychain);
+        assert!(keychain
+            .get("office@example.com", "access_token")
+            .unwrap()
+            .is_none());
+        assert!(keychain
+            .get("test@example.com", "access_token")
+            .unwrap()
+            .is_some());
+    }
 }
@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+/// Base URL of the La Marzocco customer app API
+pub const DEFAULT_BASE_URL: &str = "https://lion.lamarzocco.io/api/customer-app";
+
+/// A region served by its own La Marzocco customer app API host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Region {
+    /// Europe, served by the default host
+    #[default]
+    Eu,
+    /// The United States
+    Us,
+}
+
+impl Region {
+    /// Base URL of the customer app API for this region
+    ///
+    /// The US host follows the naming of the European one, but hasn't been confirmed against
+    /// the live API.
+    pub fn base_url(&self) -> &'static str {
+        match self {
+            Region::Eu => DEFAULT_BASE_URL,
+            Region::Us => "https://lion-us.lamarzocco.io/api/customer-app",
+        }
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Region::Eu => write!(f, "eu"),
+            Region::Us => write!(f, "us"),
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "eu" => Ok(Region::Eu),
+            "us" => Ok(Region::Us),
+            _ => Err(format!("Unknown region '{}'. Expected eu or us", s)),
+        }
+    }
+}
+
+/// URLs of the La Marzocco API endpoints used by the clients
+///
+/// All paths are built from a single base URL, so the whole API can be retargeted (e.g. at
+/// a mock server) or moved to a new version prefix in one place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub base_url: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self::for_region(Region::default())
+    }
+}
+
+impl Endpoints {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Endpoints on the API host for a region
+    pub fn for_region(region: Region) -> Self {
+        Self::new(region.base_url())
+    }
+
+    /// Register a new installation key
+    pub fn init(&self) -> String {
+        format!("{}/auth/init", self.base_url)
+    }
+
+    /// Sign in with a username and password
+    pub fn signin(&self) -> String {
+        format!("{}/auth/signin", self.base_url)
+    }
+
+    /// Exchange a refresh token for new tokens
+    pub fn refresh_token(&self) -> String {
+        format!("{}/auth/refreshtoken", self.base_url)
+    }
+
+    /// Machines connected to the account
+    pub fn things(&self) -> String {
+        format!("{}/things", self.base_url)
+    }
+
+    /// Status widgets for a machine
+    pub fn dashboard(&self, serial_number: &str) -> String {
+        format!("{}/things/{}/dashboard", self.base_url, serial_number)
+    }
+
+    /// Send a command, e.g. `CoffeeMachineChangeMode`, to a machine
+    pub fn command(&self, serial_number: &str, command: &str) -> String {
+        format!(
+            "{}/things/{}/command/{}",
+            self.base_url, serial_number, command
+        )
+    }
+
+    /// WebSocket which pushes changes to machines' dashboards as they happen
+    ///
+    /// It's at `/ws/connect` on the same host as the API, e.g.
+    /// `wss://lion.lamarzocco.io/ws/connect`.
+    pub fn realtime(&self) -> String {
+        let Ok(mut url) = reqwest::Url::parse(&self.base_url) else {
+            return format!("{}/ws/connect", self.base_url);
+        };
+        let scheme = if url.scheme() == "http" { "ws" } else { "wss" };
+        let _ = url.set_scheme(scheme);
+        url.set_path("/ws/connect");
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_endpoints() {
+        let endpoints = Endpoints::default();
+        assert_eq!(endpoints.base_url, DEFAULT_BASE_URL);
+        assert_eq!(
+            endpoints.signin(),
+            "https://lion.lamarzocco.io/api/customer-app/auth/signin"
+        );
+        assert_eq!(
+            endpoints.dashboard("MR033274"),
+            "https://lion.lamarzocco.io/api/customer-app/things/MR033274/dashboard"
+        );
+        assert_eq!(endpoints.realtime(), "wss://lion.lamarzocco.io/ws/connect");
+    }
+
+    #[test]
+    fn test_regions() {
+        assert_eq!(Endpoints::for_region(Region::Eu), Endpoints::default());
+        assert_eq!(
+            Endpoints::for_region(Region::Us).things(),
+            "https://lion-us.lamarzocco.io/api/customer-app/things"
+        );
+        assert_eq!("US".parse::<Region>(), Ok(Region::Us));
+        assert_eq!(Region::Us.to_string().parse::<Region>(), Ok(Region::Us));
+        assert!("asia".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn test_custom_base_url() {
+        // A trailing slash doesn't result in a double slash in paths
+        let endpoints = Endpoints::new("https://test.example.com/v2/");
+        assert_eq!(endpoints.base_url, "https://test.example.com/v2");
+        assert_eq!(endpoints.things(), "https://test.example.com/v2/things");
+        assert_eq!(
+            endpoints.command("MR033274", "CoffeeMachineChangeMode"),
+            "https://test.example.com/v2/things/MR033274/command/CoffeeMachineChangeMode"
+        );
+        assert_eq!(
+            Endpoints::new("http://127.0.0.1:8080").realtime(),
+            "ws://127.0.0.1:8080/ws/connect"
+        );
+    }
+}
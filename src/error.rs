@@ -0,0 +1,103 @@
+use crate::types::Capability;
+use reqwest::StatusCode;
+use std::fmt;
+
+/// Errors from the La Marzocco API which callers may want to handle differently to other
+/// failures
+///
+/// These are returned inside an [`anyhow::Error`], so use `downcast_ref::<ApiError>()` to
+/// check for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// The access token was rejected (HTTP 401), or it expired and couldn't be refreshed, so
+    /// the user needs to log in again
+    Unauthorized,
+    /// The account isn't allowed to access the resource (HTTP 403), e.g. because the machine
+    /// belongs to someone else or a subscription has lapsed. Logging in again won't help.
+    Forbidden {
+        /// What was requested, e.g. `machine MR033274`
+        resource: String,
+    },
+    /// The access token has expired and the client was built with automatic refresh turned
+    /// off, so it's up to the caller to get new tokens
+    TokenExpired,
+    /// The machine doesn't have the feature needed for the request, so it was never sent
+    UnsupportedCapability(Capability),
+}
+
+impl ApiError {
+    /// The error for an unsuccessful response status to a request for `resource`, if it's one
+    /// with a specific meaning
+    pub fn from_status(status: StatusCode, resource: &str) -> Option<Self> {
+        match status {
+            StatusCode::UNAUTHORIZED => Some(ApiError::Unauthorized),
+            StatusCode::FORBIDDEN => Some(ApiError::Forbidden {
+                resource: resource.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Unauthorized => {
+                write!(f, "Authentication failed. Please run 'lm login' again.")
+            }
+            ApiError::Forbidden { resource } => write!(
+                f,
+                "Access to {} is forbidden; your account may not be allowed to use it, or a subscription may be required.",
+                resource
+            ),
+            ApiError::TokenExpired => {
+                write!(f, "The access token has expired. Please run 'lm login' again.")
+            }
+            ApiError::UnsupportedCapability(capability) => {
+                write!(f, "This machine doesn't support {}", capability)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert_eq!(
+            ApiError::from_status(StatusCode::UNAUTHORIZED, "machine MR033274"),
+            Some(ApiError::Unauthorized)
+        );
+        assert_eq!(
+            ApiError::from_status(StatusCode::FORBIDDEN, "machine MR033274"),
+            Some(ApiError::Forbidden {
+                resource: "machine MR033274".to_string()
+            })
+        );
+        assert_eq!(
+            ApiError::from_status(StatusCode::NOT_FOUND, "machine MR033274"),
+            None
+        );
+        assert_eq!(
+            ApiError::from_status(StatusCode::INTERNAL_SERVER_ERROR, "machine MR033274"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_downcast_from_anyhow() {
+        let forbidden = ApiError::Forbidden {
+            resource: "the machines on your account".to_string(),
+        };
+        let error: anyhow::Error = forbidden.clone().into();
+        assert_eq!(error.downcast_ref::<ApiError>(), Some(&forbidden));
+        assert_eq!(
+            error.to_string(),
+            "Access to the machines on your account is forbidden; your account may not be allowed to use it, or a subscription may be required."
+        );
+    }
+}
@@ -56,6 +56,29 @@ impl InstallationKey {
 pub fn generate_installation_key(installation_id: String) -> Result<InstallationKey> {
     // Generate ECDSA private key on P-256 curve
     let secret_key = SecretKey::random(&mut OsRng);
+    installation_key_from_secret_key(installation_id, secret_key)
+}
+
+/// Generate an installation key whose private key is derived from a seed
+///
+/// The same seed and installation ID always give the same key, so tests can assert on the
+/// derived secret, base string and proofs. Use [`generate_installation_key`] for real
+/// installations.
+pub fn generate_installation_key_from_seed(
+    installation_id: String,
+    seed: &[u8],
+) -> Result<InstallationKey> {
+    let scalar = Sha256::digest(seed);
+    let secret_key = SecretKey::from_slice(&scalar)
+        .map_err(|_| anyhow::anyhow!("Seed doesn't produce a valid P-256 private key"))?;
+    installation_key_from_secret_key(installation_id, secret_key)
+}
+
+/// Derive the rest of an installation key from its private key
+fn installation_key_from_secret_key(
+    installation_id: String,
+    secret_key: SecretKey,
+) -> Result<InstallationKey> {
     let signing_key = SigningKey::from(secret_key);
     let verifying_key = *signing_key.verifying_key();
 
@@ -89,6 +112,19 @@ pub fn generate_installation_id() -> String {
     Uuid::new_v4().to_string().to_lowercase()
 }
 
+/// Generate an installation ID (a UUID v4) from a seed, rather than at random
+///
+/// The same seed always gives the same ID, for reproducible tests. Use
+/// [`generate_installation_id`] for real installations.
+pub fn generate_installation_id_from_seed(seed: &[u8]) -> String {
+    let hash = Sha256::digest(seed);
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    uuid::Builder::from_random_bytes(bytes)
+        .into_uuid()
+        .to_string()
+}
+
 /// La Marzocco's custom proof generation algorithm (Y5.e equivalent)
 pub fn generate_request_proof(base_string: &str, secret32: &[u8]) -> Result<String> {
     if secret32.len() != 32 {
@@ -106,11 +142,7 @@ pub fn generate_request_proof(base_string: &str, secret32: &[u8]) -> Result<Stri
 
         // XOR then rotate left
         let xor_result = byte_val ^ work[idx];
-        let rotated = if shift_amount == 0 {
-            xor_result
-        } else {
-            (xor_result << shift_amount) | (xor_result >> (8 - shift_amount))
-        };
+        let rotated = xor_result.rotate_left(shift_amount as u32);
         work[idx] = rotated;
     }
 
@@ -121,6 +153,34 @@ pub fn generate_request_proof(base_string: &str, secret32: &[u8]) -> Result<Stri
 }
 
 /// Generate extra headers for normal API calls after authentication
+///
+/// The La Marzocco API expects these signed headers, alongside the usual bearer token, on
+/// every request made with an installation key. They include a timestamp and a nonce, so
+/// generate them afresh for each request. To call an endpoint this crate doesn't wrap yet,
+/// attach them to your own request:
+///
+/// ```rust,no_run
+/// use lm_rs::{generate_extra_request_headers, Credentials};
+///
+/// # async fn example(credentials: Credentials) -> anyhow::Result<()> {
+/// let mut request = reqwest::Client::new()
+///     .get("https://lion.lamarzocco.io/api/customer-app/things")
+///     .bearer_auth(&credentials.access_token);
+///
+/// if let Some(installation_key) = &credentials.installation_key {
+///     for (name, value) in generate_extra_request_headers(installation_key)? {
+///         request = request.header(name, value);
+///     }
+/// }
+///
+/// let response = request.send().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// If you already have an [`ApiClient`](crate::ApiClient), its
+/// [`request_headers`](crate::ApiClient::request_headers) method builds the complete set of
+/// headers, refreshing the access token first if needed.
 pub fn generate_extra_request_headers(
     installation_key: &InstallationKey,
 ) -> Result<Vec<(String, String)>> {
@@ -171,7 +231,16 @@ where
     D: serde::Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
-    STANDARD.decode(s).map_err(serde::de::Error::custom)
+    let bytes = STANDARD
+        .decode(s)
+        .map_err(|e| serde::de::Error::custom(format!("secret isn't valid base64: {}", e)))?;
+    if bytes.len() != 32 {
+        return Err(serde::de::Error::custom(format!(
+            "secret should be 32 bytes, but is {}",
+            bytes.len()
+        )));
+    }
+    Ok(bytes)
 }
 
 fn serialize_signing_key_as_base64<S>(
@@ -191,8 +260,15 @@ where
     D: serde::Deserializer<'de>,
 {
     let s = String::deserialize(deserializer)?;
-    let bytes = STANDARD.decode(s).map_err(serde::de::Error::custom)?;
-    let secret_key = SecretKey::from_slice(&bytes).map_err(serde::de::Error::custom)?;
+    let bytes = STANDARD
+        .decode(s)
+        .map_err(|e| serde::de::Error::custom(format!("private key isn't valid base64: {}", e)))?;
+    let secret_key = SecretKey::from_slice(&bytes).map_err(|_| {
+        serde::de::Error::custom(format!(
+            "private key isn't a valid P-256 key ({} bytes)",
+            bytes.len()
+        ))
+    })?;
     Ok(SigningKey::from(secret_key))
 }
 
@@ -215,6 +291,46 @@ mod tests {
         assert!(id2.contains('-'));
     }
 
+    #[test]
+    fn test_installation_id_generation_from_seed() {
+        let id = generate_installation_id_from_seed(b"test seed");
+
+        assert_eq!(id, generate_installation_id_from_seed(b"test seed"));
+        assert_ne!(id, generate_installation_id_from_seed(b"another seed"));
+
+        let uuid = Uuid::parse_str(&id).unwrap();
+        assert_eq!(uuid.get_version_num(), 4);
+        assert_eq!(id, uuid.to_string());
+    }
+
+    #[test]
+    fn test_installation_key_generation_from_seed() {
+        let installation_id = generate_installation_id_from_seed(b"test seed");
+        let key =
+            generate_installation_key_from_seed(installation_id.clone(), b"test seed").unwrap();
+        let same_key =
+            generate_installation_key_from_seed(installation_id.clone(), b"test seed").unwrap();
+        let other_key =
+            generate_installation_key_from_seed(installation_id.clone(), b"another seed").unwrap();
+
+        assert_eq!(key.secret, same_key.secret);
+        assert_eq!(key.base_string(), same_key.base_string());
+        assert_eq!(
+            generate_request_proof(&key.base_string(), &key.secret).unwrap(),
+            generate_request_proof(&same_key.base_string(), &same_key.secret).unwrap()
+        );
+
+        assert_ne!(key.secret, other_key.secret);
+        assert_ne!(key.public_key_b64(), other_key.public_key_b64());
+
+        // Pin the derived values, so a change to the derivation is caught
+        assert_eq!(installation_id, "235be5ab-8ea6-427a-89e1-b5c8b98a53ea");
+        assert_eq!(
+            STANDARD.encode(&key.secret),
+            "+hB38XSC9Pd+WbcDyBafxJPU2JaGhGwOYAzFO7pcy7A="
+        );
+    }
+
     #[test]
     fn test_installation_key_generation() {
         let installation_id = "test-installation-id".to_string();
@@ -8,6 +8,12 @@
 //! - Turn machines on and off remotely
 //! - Automatic JWT token management with expiration checking
 //! - Token refresh callbacks for custom token persistence
+//! - Token lifecycle observers, e.g. to alert when a refresh fails
+//! - Shared token stores so several processes can use one account safely
+//! - Exponential backoff for your own polling loops
+//! - Retrying requests which fail with server or network errors
+//! - Real-time machine status over a WebSocket, reconnecting when the connection drops
+//! - Signed request headers for calling endpoints this crate doesn't wrap yet
 //!
 //! ## Library Usage
 //!
@@ -41,23 +47,49 @@
 //! # }
 //! ```
 //!
+//! ## Calling other endpoints
+//!
+//! To call an endpoint of the La Marzocco API which this crate doesn't wrap yet, get the
+//! headers for your request from [`ApiClient::request_headers`], or build them from your
+//! own [`Credentials`] with [`generate_extra_request_headers`]. The lower-level
+//! [`generate_request_proof`] is exported too.
+//!
 //! ## CLI Usage
 //!
 //! The main functionality is also provided through the CLI binary for direct command-line usage.
 
 pub mod auth;
+pub mod backoff;
 pub mod client;
 pub mod config;
+pub mod endpoints;
+pub mod error;
+pub mod http;
 pub mod installation_key;
+mod realtime;
+pub mod token_store;
 pub mod types;
 
 // Export new library interface
-pub use auth::{is_token_expired, ApiClient, AuthenticationClient, TokenRefreshCallback};
+pub use auth::{
+    is_token_expired, token_expiry, ApiClient, AuthenticationClient, RefreshCallbackObserver,
+    TokenLifecycleObserver, TokenRefreshCallback, DEFAULT_CONCURRENCY,
+};
+pub use backoff::RetryPolicy;
+pub use endpoints::{Endpoints, Region};
+pub use error::ApiError;
+pub use http::{HttpOptions, IpVersion};
 pub use installation_key::{
-    generate_extra_request_headers, generate_installation_id, generate_installation_key,
-    generate_request_proof, InstallationKey,
+    generate_extra_request_headers, generate_installation_id, generate_installation_id_from_seed,
+    generate_installation_key, generate_installation_key_from_seed, generate_request_proof,
+    InstallationKey,
+};
+pub use token_store::{FileTokenStore, RefreshGuard, TokenStore};
+pub use types::{
+    Alarm, BoilerState, BoilerTemperature, BrewByWeight, BrewTime, BrewTimes, Capability,
+    CommandJob, Credentials, Grinder, GrinderStatus, MachineState, MachineStatusSummary,
+    MachineSummary, TemperatureCommand, WaterConfig, WaterLevel,
 };
-pub use types::Credentials;
 
 // Export legacy interface for backward compatibility
 pub use client::LaMarzoccoClient;
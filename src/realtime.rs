@@ -0,0 +1,376 @@
+//! Real-time machine status, pushed by La Marzocco over a WebSocket whenever it changes
+//!
+//! The WebSocket speaks [STOMP](https://stomp.github.io/stomp-specification-1.2.html): the
+//! client sends a `CONNECT` frame with its access token, subscribes to a machine's dashboard,
+//! and then gets a `MESSAGE` frame with the whole dashboard each time something changes. Use
+//! [`ApiClient::subscribe_status`](crate::ApiClient::subscribe_status) rather than this
+//! module directly.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use log::debug;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::http::{HttpOptions, IpVersion, DEFAULT_TIMEOUT};
+use crate::types::MachineStatus;
+
+/// How often the server is asked to send a heartbeat, so an idle connection can be told
+/// apart from a dead one
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait without hearing anything before pinging the server, and then how long to
+/// wait for a reply before treating the connection as dropped
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An open WebSocket, subscribed to a machine's dashboard
+pub(crate) type Subscription = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A STOMP frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub command: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Frame {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The value of a header. If a header is repeated, the first value wins, as in the spec.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Encode the frame to send as a text message
+    pub fn encode(&self) -> String {
+        let mut frame = format!("{}\n", self.command);
+        for (name, value) in &self.headers {
+            frame.push_str(&format!("{}:{}\n", name, value));
+        }
+        frame.push('\n');
+        frame.push_str(&self.body);
+        frame.push('\0');
+        frame
+    }
+
+    /// Parse a frame from a text message, or `None` for a heartbeat or anything else which
+    /// isn't a frame
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim_start_matches(['\r', '\n']);
+        let text = text.strip_suffix('\0').unwrap_or(text);
+        let (head, body) = text
+            .split_once("\r\n\r\n")
+            .or_else(|| text.split_once("\n\n"))
+            .unwrap_or((text, ""));
+
+        let mut lines = head.lines();
+        let command = lines.next()?.trim_end_matches('\r');
+        if command.is_empty() {
+            return None;
+        }
+
+        let headers = lines
+            .filter_map(|line| line.trim_end_matches('\r').split_once(':'))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        Some(Self {
+            command: command.to_string(),
+            headers,
+            body: body.to_string(),
+        })
+    }
+}
+
+/// The frame which opens a STOMP session, authenticated with an access token
+pub(crate) fn connect_frame(host: &str, access_token: &str) -> Frame {
+    Frame::new("CONNECT")
+        .with_header("host", host)
+        .with_header("accept-version", "1.2,1.1,1.0")
+        // We don't send heartbeats, but ask for them so a dead connection is noticed
+        .with_header(
+            "heart-beat",
+            &format!("0,{}", HEARTBEAT_INTERVAL.as_millis()),
+        )
+        .with_header("Authorization", &format!("Bearer {}", access_token))
+}
+
+/// The frame which subscribes to changes to a machine's dashboard
+pub(crate) fn subscribe_frame(serial_number: &str, id: &str) -> Frame {
+    Frame::new("SUBSCRIBE")
+        .with_header(
+            "destination",
+            &format!("/ws/sn/{}/dashboard", serial_number),
+        )
+        .with_header("ack", "auto")
+        .with_header("id", id)
+        .with_header("content-length", "0")
+}
+
+/// Connect to the WebSocket at `url` and subscribe to a machine's dashboard
+///
+/// `headers` (e.g. the bearer token and signed installation key headers) are sent with the
+/// WebSocket handshake, and the access token again in the STOMP `CONNECT` frame. The IP
+/// version and timeout from `http_options` are respected. WebSockets can't go through a
+/// proxy, so this fails if one is configured, rather than bypassing it.
+pub(crate) async fn subscribe(
+    url: &str,
+    headers: reqwest::header::HeaderMap,
+    access_token: &str,
+    serial_number: &str,
+    http_options: &HttpOptions,
+) -> Result<Subscription> {
+    if uses_proxy(http_options) {
+        return Err(anyhow::anyhow!(
+            "Real-time updates can't be received through a proxy"
+        ));
+    }
+
+    let timeout = http_options.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    tokio::time::timeout(
+        timeout,
+        connect(url, headers, access_token, serial_number, http_options),
+    )
+    .await
+    .unwrap_or_else(|_| {
+        Err(anyhow::anyhow!(
+            "Failed to connect to {}: timed out after {}s",
+            url,
+            timeout.as_secs()
+        ))
+    })
+}
+
+/// Whether requests would go through a proxy, set in `http_options` or the environment
+fn uses_proxy(http_options: &HttpOptions) -> bool {
+    if http_options.no_proxy {
+        return false;
+    }
+    http_options.proxy.is_some()
+        || ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+            .iter()
+            .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+async fn connect(
+    url: &str,
+    headers: reqwest::header::HeaderMap,
+    access_token: &str,
+    serial_number: &str,
+    http_options: &HttpOptions,
+) -> Result<Subscription> {
+    let mut request = url
+        .into_client_request()
+        .with_context(|| format!("Invalid WebSocket URL '{}'", url))?;
+    request.headers_mut().extend(headers);
+    let host = request.uri().host().unwrap_or_default().to_string();
+    let port = request
+        .uri()
+        .port_u16()
+        .unwrap_or(match request.uri().scheme_str() {
+            Some("wss") => 443,
+            _ => 80,
+        });
+
+    debug!("Connecting to {}", url);
+    let stream = connect_tcp(&host, port, http_options.ip_version)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+    let (mut socket, _) = tokio_tungstenite::client_async_tls(request, stream)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+
+    socket
+        .send(Message::text(connect_frame(&host, access_token).encode()))
+        .await?;
+    loop {
+        match next_frame(&mut socket).await? {
+            Some(frame) if frame.command == "CONNECTED" => break,
+            Some(frame) if frame.command == "ERROR" => return Err(frame_error(&frame)),
+            Some(frame) => debug!("Ignoring {} frame before CONNECTED", frame.command),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "The real-time connection closed before it was set up"
+                ))
+            }
+        }
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    socket
+        .send(Message::text(subscribe_frame(serial_number, &id).encode()))
+        .await?;
+    debug!("Subscribed to the dashboard of {}", serial_number);
+
+    Ok(socket)
+}
+
+/// Open a TCP connection to the first address of `host` which accepts one, only trying
+/// addresses of `ip_version` if it's set
+async fn connect_tcp(host: &str, port: u16, ip_version: Option<IpVersion>) -> Result<TcpStream> {
+    let addresses =
+        tokio::net::lookup_host((host, port))
+            .await?
+            .filter(|address| match ip_version {
+                Some(IpVersion::V4) => address.is_ipv4(),
+                Some(IpVersion::V6) => address.is_ipv6(),
+                None => true,
+            });
+
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect(address).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => e.into(),
+        None => anyhow::anyhow!("No addresses found for {}", host),
+    })
+}
+
+/// Wait for the next status pushed to a subscription
+///
+/// Returns `None` once the connection is closed. A message which can't be parsed is returned
+/// as an error, but the connection can still be used.
+pub(crate) async fn next_status(socket: &mut Subscription) -> Option<Result<MachineStatus>> {
+    loop {
+        let frame = match next_frame(socket).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return None,
+            Err(e) => {
+                debug!("Real-time connection failed: {}", e);
+                return None;
+            }
+        };
+
+        match frame.command.as_str() {
+            "MESSAGE" => {
+                return Some(
+                    serde_json::from_str::<MachineStatus>(&frame.body)
+                        .context("Failed to parse machine status from the real-time connection"),
+                )
+            }
+            "ERROR" => {
+                debug!("{}", frame_error(&frame));
+                return None;
+            }
+            command => debug!("Ignoring {} frame", command),
+        }
+    }
+}
+
+/// Read frames until one arrives, skipping heartbeats and WebSocket control messages
+///
+/// If nothing arrives for [`IDLE_TIMEOUT`], the server is pinged, and if it still doesn't
+/// reply, the connection is treated as dropped.
+async fn next_frame(socket: &mut Subscription) -> Result<Option<Frame>> {
+    let mut pinged = false;
+    loop {
+        let message = match tokio::time::timeout(IDLE_TIMEOUT, socket.next()).await {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(None),
+            Err(_) if !pinged => {
+                debug!("Nothing received for {:?}, pinging", IDLE_TIMEOUT);
+                socket.send(Message::Ping(Default::default())).await?;
+                pinged = true;
+                continue;
+            }
+            Err(_) => {
+                return Err(anyhow::anyhow!(
+                    "The real-time connection stopped responding"
+                ))
+            }
+        };
+        pinged = false;
+
+        let text = match message? {
+            Message::Text(text) => text.as_str().to_string(),
+            Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Message::Close(_) => return Ok(None),
+            _ => continue,
+        };
+        if let Some(frame) = Frame::parse(&text) {
+            return Ok(Some(frame));
+        }
+    }
+}
+
+fn frame_error(frame: &Frame) -> anyhow::Error {
+    let message = frame
+        .header("message")
+        .filter(|message| !message.is_empty())
+        .unwrap_or(frame.body.as_str());
+    anyhow::anyhow!("The real-time connection was refused: {}", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frame = subscribe_frame("MR033274", "sub-1");
+        assert_eq!(
+            frame.encode(),
+            "SUBSCRIBE\ndestination:/ws/sn/MR033274/dashboard\nack:auto\nid:sub-1\ncontent-length:0\n\n\0"
+        );
+        assert_eq!(Frame::parse(&frame.encode()), Some(frame));
+    }
+
+    #[test]
+    fn test_parse_message_frame() {
+        let frame = Frame::parse(
+            "MESSAGE\r\ndestination:/ws/sn/MR033274/dashboard\r\nmessage-id:1\r\n\r\n{\"widgets\":[]}\0",
+        )
+        .unwrap();
+        assert_eq!(frame.command, "MESSAGE");
+        assert_eq!(
+            frame.header("destination"),
+            Some("/ws/sn/MR033274/dashboard")
+        );
+        assert_eq!(frame.body, "{\"widgets\":[]}");
+    }
+
+    #[test]
+    fn test_parse_heartbeat() {
+        assert_eq!(Frame::parse("\n"), None);
+        assert_eq!(Frame::parse(""), None);
+    }
+
+    #[test]
+    fn test_connect_frame() {
+        let frame = connect_frame("lion.lamarzocco.io", "token123");
+        assert_eq!(frame.header("host"), Some("lion.lamarzocco.io"));
+        assert_eq!(frame.header("Authorization"), Some("Bearer token123"));
+    }
+
+    #[test]
+    fn test_frame_error_prefers_message_header() {
+        let frame = Frame::new("ERROR").with_header("message", "Bad token");
+        assert_eq!(
+            frame_error(&frame).to_string(),
+            "The real-time connection was refused: Bad token"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs4::fs_std::FileExt;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::installation_key::InstallationKey;
+use crate::types::Credentials;
+
+/// Shared storage for credentials that several processes (e.g. a daemon and the CLI) can use
+///
+/// `ApiClient` consults the store before refreshing an expired access token, so a token
+/// refreshed by another process is picked up instead of burning the single-use refresh token
+/// a second time. After a successful refresh, the new credentials are written back.
+pub trait TokenStore: Send + Sync {
+    /// Load the most recently stored credentials for a user, if there are any
+    fn load(&self, username: &str) -> Result<Option<Credentials>>;
+
+    /// Persist credentials, replacing anything previously stored for the same user
+    fn store(&self, credentials: &Credentials) -> Result<()>;
+
+    /// Take an exclusive lock for a whole token refresh, so only one process refreshes at a
+    /// time
+    ///
+    /// The lock is held until the returned guard is dropped. Meanwhile, other processes wait
+    /// for it, then pick up the refreshed tokens with `load` rather than spending the
+    /// single-use refresh token again. The default implementation doesn't lock anything.
+    fn lock_for_refresh(&self) -> Result<RefreshGuard> {
+        Ok(Box::new(()))
+    }
+}
+
+/// Guard returned by [`TokenStore::lock_for_refresh`], which releases the lock when dropped
+pub type RefreshGuard = Box<dyn Send>;
+
+/// Tokens for a single user as persisted by `FileTokenStore`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredTokens {
+    access_token: String,
+    refresh_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    installation_key: Option<InstallationKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token_obtained_at: Option<DateTime<Utc>>,
+}
+
+/// YAML file-backed token store keyed by username, guarded with advisory file locks
+///
+/// On Unix, the file is created so only the current user can read it.
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Path of the file backing this store
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the file locked while refreshing, next to the store itself
+    ///
+    /// This is separate from the store so that `load` and `store` can still take their own
+    /// locks while a refresh is in progress.
+    fn refresh_lock_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".lock");
+        PathBuf::from(path)
+    }
+
+    fn read_entries(file: &mut File) -> Result<BTreeMap<String, StoredTokens>> {
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .context("Failed to read token store")?;
+
+        if content.trim().is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        serde_yaml::from_str(&content).context("Failed to parse token store")
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self, username: &str) -> Result<Option<Credentials>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let mut file = File::open(&self.path)
+            .with_context(|| format!("Failed to open token store: {}", self.path.display()))?;
+        FileExt::lock_shared(&file).context("Failed to lock token store")?;
+
+        let entries = Self::read_entries(&mut file)?;
+        debug!("Loaded token store with {} entries", entries.len());
+
+        Ok(entries.get(username).map(|tokens| Credentials {
+            access_token: tokens.access_token.clone(),
+            refresh_token: tokens.refresh_token.clone(),
+            username: username.to_string(),
+            installation_key: tokens.installation_key.clone(),
+            token_obtained_at: tokens.token_obtained_at,
+        }))
+    }
+
+    fn store(&self, credentials: &Credentials) -> Result<()> {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(false);
+        // The file holds tokens, so only the current user should be able to read it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&self.path)
+            .with_context(|| format!("Failed to open token store: {}", self.path.display()))?;
+        FileExt::lock_exclusive(&file).context("Failed to lock token store")?;
+
+        // Re-read under the exclusive lock so entries for other users are preserved
+        let mut entries = Self::read_entries(&mut file)?;
+        entries.insert(
+            credentials.username.clone(),
+            StoredTokens {
+                access_token: credentials.access_token.clone(),
+                refresh_token: credentials.refresh_token.clone(),
+                installation_key: credentials.installation_key.clone(),
+                token_obtained_at: credentials.token_obtained_at,
+            },
+        );
+
+        let content = serde_yaml::to_string(&entries).context("Failed to serialize tokens")?;
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(content.as_bytes())
+            .with_context(|| format!("Failed to write token store: {}", self.path.display()))?;
+
+        debug!("Stored tokens for user: {}", credentials.username);
+        Ok(())
+    }
+
+    fn lock_for_refresh(&self) -> Result<RefreshGuard> {
+        let path = self.refresh_lock_path();
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open token store lock: {}", path.display()))?;
+        FileExt::lock_exclusive(&file).context("Failed to lock token store for refresh")?;
+
+        debug!("Locked token store for refresh");
+        Ok(Box::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn credentials(username: &str, access_token: &str) -> Credentials {
+        Credentials {
+            access_token: access_token.to_string(),
+            refresh_token: format!("{}-refresh", access_token),
+            username: username.to_string(),
+            installation_key: None,
+            token_obtained_at: None,
+        }
+    }
+
+    #[test]
+    fn test_load_from_missing_file_returns_none() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FileTokenStore::new(temp_dir.path().join("tokens.yml"));
+
+        assert!(store.load("test@example.com").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_store_and_load_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FileTokenStore::new(temp_dir.path().join("tokens.yml"));
+
+        store
+            .store(&credentials("test@example.com", "access123"))
+            .unwrap();
+
+        let loaded = store.load("test@example.com").unwrap().unwrap();
+        assert_eq!(loaded.username, "test@example.com");
+        assert_eq!(loaded.access_token, "access123");
+        assert_eq!(loaded.refresh_token, "access123-refresh");
+        assert!(store.load("other@example.com").unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_creates_file_readable_only_by_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tokens.yml");
+        FileTokenStore::new(path.clone())
+            .store(&credentials("test@example.com", "access123"))
+            .unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_refresh_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tokens.yml");
+        let guard = FileTokenStore::new(&path).lock_for_refresh().unwrap();
+
+        // The store can still be used while the refresh lock is held
+        let store = FileTokenStore::new(&path);
+        store
+            .store(&credentials("test@example.com", "access123"))
+            .unwrap();
+        assert!(store.load("test@example.com").unwrap().is_some());
+
+        let lock = File::open(store.refresh_lock_path()).unwrap();
+        assert!(!FileExt::try_lock_exclusive(&lock).unwrap());
+        drop(guard);
+        assert!(FileExt::try_lock_exclusive(&lock).unwrap());
+    }
+
+    #[test]
+    fn test_store_keeps_entries_for_other_users() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = FileTokenStore::new(temp_dir.path().join("tokens.yml"));
+
+        store
+            .store(&credentials("a@example.com", "access-a"))
+            .unwrap();
+        store
+            .store(&credentials("b@example.com", "access-b"))
+            .unwrap();
+        store
+            .store(&credentials("a@example.com", "access-a2"))
+            .unwrap();
+
+        assert_eq!(
+            store.load("a@example.com").unwrap().unwrap().access_token,
+            "access-a2"
+        );
+        assert_eq!(
+            store.load("b@example.com").unwrap().unwrap().access_token,
+            "access-b"
+        );
+    }
+}
@@ -1,16 +1,73 @@
-use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
 
 /// Authentication tokens returned from login
+///
+/// Use [`Credentials::new`] to build them yourself, e.g. from tokens you've stored, as
+/// fields may be added in future versions.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Credentials {
     pub access_token: String,
     pub refresh_token: String,
     pub username: String,
     /// Installation key for new authentication system
     pub installation_key: Option<crate::installation_key::InstallationKey>,
+    /// When these tokens were obtained by logging in or refreshing, if known
+    pub token_obtained_at: Option<DateTime<Utc>>,
 }
 
+impl Credentials {
+    /// Tokens for `username`, with no installation key and an unknown age
+    pub fn new(
+        access_token: impl Into<String>,
+        refresh_token: impl Into<String>,
+        username: impl Into<String>,
+    ) -> Self {
+        Self {
+            access_token: access_token.into(),
+            refresh_token: refresh_token.into(),
+            username: username.into(),
+            installation_key: None,
+            token_obtained_at: None,
+        }
+    }
+
+    /// Sign requests with this installation key
+    pub fn with_installation_key(
+        mut self,
+        installation_key: crate::installation_key::InstallationKey,
+    ) -> Self {
+        self.installation_key = Some(installation_key);
+        self
+    }
+
+    /// Record when these tokens were obtained, so [`Credentials::is_older_than`] can check them
+    pub fn with_token_obtained_at(mut self, obtained_at: DateTime<Utc>) -> Self {
+        self.token_obtained_at = Some(obtained_at);
+        self
+    }
+
+    /// Check whether these tokens were obtained more than `max_age` before `now`
+    ///
+    /// Tokens of unknown age (e.g. from a config file written by an older version) count as
+    /// too old, so a maximum age can't be sidestepped.
+    pub fn is_older_than(&self, max_age: Duration, now: DateTime<Utc>) -> bool {
+        match self.token_obtained_at {
+            Some(obtained_at) => (now - obtained_at).to_std().is_ok_and(|age| age > max_age),
+            None => true,
+        }
+    }
+}
+
+/// A machine connected to the account
+///
+/// Machines are compared and hashed by serial number only, so the same machine fetched at
+/// different times is considered equal even if its name or connection state changed.
 #[derive(Debug, Deserialize, Clone)]
 pub struct Machine {
     #[serde(rename = "serialNumber")]
@@ -18,9 +75,90 @@ pub struct Machine {
     #[serde(rename = "modelName")]
     pub model: Option<String>,
     pub name: Option<String>,
-    #[allow(dead_code)] // Field from API but not currently used in display
     pub location: Option<String>,
     pub connected: bool,
+    /// The kind of device, e.g. "CoffeeMachine" or "Grinder"
+    #[serde(rename = "type", default)]
+    pub device_type: Option<String>,
+}
+
+/// The basic details of a machine on the account, e.g. for menus or listing machines in
+/// scripts, without its status
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MachineSummary {
+    pub serial: String,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub connected: bool,
+}
+
+impl From<&Machine> for MachineSummary {
+    fn from(machine: &Machine) -> Self {
+        Self {
+            serial: machine.serial_number.clone(),
+            name: machine.name.clone(),
+            model: machine.model.clone(),
+            connected: machine.connected,
+        }
+    }
+}
+
+/// A grinder on the account, like a Pico or Swift
+///
+/// Grinders are listed alongside espresso machines, but can't be controlled the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grinder {
+    pub serial_number: String,
+    pub model: Option<String>,
+    pub name: Option<String>,
+    pub connected: bool,
+}
+
+impl Grinder {
+    /// The `type` of things which are grinders
+    pub const DEVICE_TYPE: &'static str = "Grinder";
+
+    /// The grinder described by a thing from the account, if it is one
+    pub fn from_thing(thing: &Machine) -> Option<Self> {
+        if thing.device_type.as_deref() != Some(Self::DEVICE_TYPE) {
+            return None;
+        }
+
+        Some(Self {
+            serial_number: thing.serial_number.clone(),
+            model: thing.model.clone(),
+            name: thing.name.clone(),
+            connected: thing.connected,
+        })
+    }
+}
+
+/// Status of a grinder, from its dashboard
+///
+/// Grinder dashboards use the same widget format as espresso machines, but with their own
+/// widget codes, so the widgets are exposed as they are.
+#[derive(Debug, Deserialize)]
+pub struct GrinderStatus {
+    #[serde(default, deserialize_with = "lenient_widgets")]
+    pub widgets: Vec<Widget>,
+}
+
+impl GrinderStatus {
+    /// The statuses reported by the grinder's widgets, e.g. "Ready", or "Unknown" if there
+    /// aren't any
+    pub fn status_string(&self) -> String {
+        let statuses: Vec<&str> = self
+            .widgets
+            .iter()
+            .filter_map(|widget| widget.output.as_ref()?.status.as_deref())
+            .collect();
+
+        if statuses.is_empty() {
+            "Unknown".to_string()
+        } else {
+            statuses.join(", ")
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,30 +166,532 @@ pub struct MachinesResponse {
     pub things: Vec<Machine>,
 }
 
+/// A command which the API accepted (with `202 Accepted`) to run asynchronously
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CommandJob {
+    pub id: String,
+    /// Progress of the command, e.g. `Pending`, `Success` or `Error`
+    #[serde(default, deserialize_with = "lenient")]
+    pub status: Option<String>,
+    /// Why the command failed, if it did
+    #[serde(rename = "errorCode", default, deserialize_with = "lenient")]
+    pub error_code: Option<String>,
+}
+
+impl CommandJob {
+    /// Parse a job from a response body, which may be a single job or a list with one job
+    pub fn from_response(body: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(body).ok().or_else(|| {
+            serde_json::from_str::<Vec<Self>>(body)
+                .ok()
+                .and_then(|jobs| jobs.into_iter().next())
+        })
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct MachineCommand {
     pub mode: String,
 }
 
+/// Body of a `CoffeeMachineSettingCoffeeBoiler` command, which sets a boiler's target
+/// temperature
+#[derive(Debug, Serialize)]
+pub struct TemperatureCommand {
+    #[serde(rename = "boilerIndex")]
+    pub boiler_index: u8,
+    #[serde(rename = "targetTemperature")]
+    pub target_temperature: f32,
+}
+
+/// Status of a machine, as returned by the dashboard endpoint
+///
+/// The shape of the dashboard varies between firmware versions, so parsing is lenient:
+/// widgets which can't be parsed are skipped, and fields with an unexpected type are treated
+/// as missing rather than failing the whole response.
 #[derive(Debug, Deserialize)]
 pub struct MachineStatus {
+    #[serde(default, deserialize_with = "lenient_widgets")]
     pub widgets: Vec<Widget>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Widget {
     pub code: String,
+    #[serde(default, deserialize_with = "lenient")]
     pub output: Option<WidgetOutput>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Default)]
 pub struct WidgetOutput {
+    #[serde(default, deserialize_with = "lenient")]
     pub status: Option<String>,
-    #[allow(dead_code)]
+    #[serde(default, deserialize_with = "lenient")]
     pub mode: Option<String>,
     // Boiler-specific fields
-    #[serde(rename = "readyStartTime")]
+    #[serde(rename = "readyStartTime", default, deserialize_with = "lenient_u64")]
     pub ready_start_time: Option<u64>,
+    #[serde(
+        rename = "targetTemperature",
+        default,
+        deserialize_with = "lenient_f64"
+    )]
+    pub target_temperature: Option<f64>,
+    #[serde(
+        rename = "currentTemperature",
+        default,
+        deserialize_with = "lenient_f64"
+    )]
+    pub current_temperature: Option<f64>,
+    // Water filter-specific fields
+    #[serde(rename = "waterHardness", default, deserialize_with = "lenient_u8")]
+    pub water_hardness: Option<u8>,
+    #[serde(rename = "filterType", default, deserialize_with = "lenient")]
+    pub filter_type: Option<String>,
+    #[serde(
+        rename = "filterRemainingCapacity",
+        default,
+        deserialize_with = "lenient_f64"
+    )]
+    pub filter_remaining_capacity: Option<f64>,
+    // Alarm-specific fields
+    #[serde(default, deserialize_with = "lenient")]
+    pub alarms: Option<Vec<Alarm>>,
+    // Brew by weight-specific fields
+    #[serde(rename = "scaleConnected", default, deserialize_with = "lenient")]
+    pub scale_connected: Option<bool>,
+    #[serde(rename = "targetWeight", default, deserialize_with = "lenient_f64")]
+    pub target_weight: Option<f64>,
+    // Connectivity-specific fields
+    /// Strength of the machine's Wi-Fi signal in dBm, e.g. -60
+    #[serde(default, deserialize_with = "lenient_i32")]
+    pub rssi: Option<i32>,
+    // Dosing-specific fields
+    /// Doses for each button, keyed by how they're measured, e.g. `TimeType`
+    #[serde(default, deserialize_with = "lenient")]
+    pub doses: Option<HashMap<String, Vec<Dose>>>,
+    // Pressure-specific fields
+    /// Pressure in bar. The `CMBrewing` widget's is the pump pressure, which some machines
+    /// report while brewing.
+    #[serde(default, deserialize_with = "lenient_f64")]
+    pub pressure: Option<f64>,
+    // Water tank-specific fields
+    /// Whether the `CMNoWater` widget's alarm is raised, i.e. the water tank is empty. The
+    /// API spells it `allarm`.
+    #[serde(rename = "allarm", default, deserialize_with = "lenient")]
+    pub no_water_alarm: Option<bool>,
+}
+
+/// Deserialize a value, treating a value of the wrong type as missing
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Deserialize a list of widgets, skipping any which can't be parsed
+fn lenient_widgets<'de, D>(deserializer: D) -> Result<Vec<Widget>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let widgets = match value {
+        serde_json::Value::Array(widgets) => widgets,
+        _ => return Ok(Vec::new()),
+    };
+
+    Ok(widgets
+        .into_iter()
+        .filter_map(|widget| serde_json::from_value(widget).ok())
+        .collect())
+}
+
+/// Read a number which may be sent either as a JSON number or a string
+fn number_from_value(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|number: &f64| number.is_finite())
+}
+
+/// Deserialize a non-negative integer sent either as a number or a string
+fn lenient_u64<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    // Prefer the exact integer, falling back to parsing (e.g. for strings or floats)
+    Ok(value.as_u64().or_else(|| {
+        number_from_value(&value)
+            .filter(|number| *number >= 0.0 && *number < u64::MAX as f64)
+            .map(|number| number as u64)
+    }))
+}
+
+/// Deserialize a small integer sent either as a number or a string
+fn lenient_u8<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(lenient_u64(deserializer)?.and_then(|number| u8::try_from(number).ok()))
+}
+
+/// Deserialize a signed integer sent either as a number or a string
+fn lenient_i32<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(number_from_value(&value)
+        .filter(|number| *number >= i32::MIN as f64 && *number <= i32::MAX as f64)
+        .map(|number| number.round() as i32))
+}
+
+/// Deserialize a decimal number sent either as a number or a string
+fn lenient_f64<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(number_from_value(&value))
+}
+
+/// An alarm raised by a machine, e.g. a faulty boiler probe
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Alarm {
+    pub code: String,
+    pub description: Option<String>,
+}
+
+/// State of the coffee boiler, parsed from the `CMCoffeeBoiler` widget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoilerState {
+    /// Up to temperature and ready to brew
+    Ready,
+    /// Warming up
+    Heating,
+    /// The boiler can't heat because the water reservoir is empty
+    NoWater,
+    /// Switched off, e.g. because the machine is in standby
+    Off,
+    /// A state this version doesn't know about, with the raw value from the API
+    Unknown(String),
+}
+
+impl std::fmt::Display for BoilerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoilerState::Ready => write!(f, "Ready"),
+            BoilerState::Heating => write!(f, "Heating"),
+            BoilerState::NoWater => write!(f, "No water"),
+            BoilerState::Off => write!(f, "Off"),
+            BoilerState::Unknown(status) => write!(f, "{}", status),
+        }
+    }
+}
+
+impl From<&str> for BoilerState {
+    fn from(status: &str) -> Self {
+        match status {
+            "Ready" => BoilerState::Ready,
+            "Heating" | "HeatingUp" => BoilerState::Heating,
+            "NoWater" => BoilerState::NoWater,
+            "StandBy" | "Off" => BoilerState::Off,
+            other => BoilerState::Unknown(other.to_string()),
+        }
+    }
+}
+
+/// What a machine is doing, combining its `CMMachineStatus` widget with the state of its
+/// coffee boiler and water tank
+///
+/// Match on this to decide what to do, rather than on the text from
+/// [`MachineStatus::get_status_string`], which is for display. The `Display` output is the
+/// same text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineState {
+    /// In standby, with the boilers off
+    Standby,
+    /// On, with the coffee boiler warming up. `ready_in` is how long until it expects to be
+    /// ready, if the machine says.
+    Heating { ready_in: Option<Duration> },
+    /// On, with the coffee boiler up to temperature
+    Ready,
+    /// On, but the water tank needs refilling before it can heat up or brew
+    NoWater,
+    /// Pulling a shot
+    Brewing,
+    /// On, but the machine doesn't report its coffee boiler, so it isn't known whether it's
+    /// ready
+    On,
+    /// A state this version doesn't know about, with the raw machine or boiler status from
+    /// the API, or `None` if the machine didn't report its status
+    Unknown(Option<String>),
+}
+
+impl std::fmt::Display for MachineState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MachineState::Standby => write!(f, "Standby"),
+            MachineState::Heating {
+                ready_in: Some(ready_in),
+            } => match ready_in.as_secs() / 60 {
+                // Includes a ready time in the past, when it should be ready soon
+                0 => write!(f, "On (Ready in < 1 min)"),
+                1 => write!(f, "On (Ready in 1 min)"),
+                minutes_remaining => write!(f, "On (Ready in {} mins)", minutes_remaining),
+            },
+            MachineState::Heating { ready_in: None } => write!(f, "On (Ready soon)"),
+            MachineState::Ready => write!(f, "On (Ready)"),
+            MachineState::NoWater => write!(f, "On (No water)"),
+            MachineState::Brewing => write!(f, "Brewing"),
+            MachineState::On => write!(f, "On"),
+            MachineState::Unknown(Some(status)) => write!(f, "Unknown ({})", status),
+            MachineState::Unknown(None) => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// Water hardness and filter configuration of a machine, from the `CMWaterFilter` widget
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaterConfig {
+    /// Water hardness level configured on the machine
+    pub hardness_level: Option<u8>,
+    /// Type of water filter fitted, e.g. "BWTBestmax" or "NoFilter"
+    pub filter_type: Option<String>,
+    /// Remaining capacity of the filter in litres
+    pub filter_remaining_capacity: Option<f64>,
+}
+
+/// How much water is in a machine's tank, from the `CMNoWater` widget or the coffee boiler
+///
+/// Machines only say whether the tank has run out, not how full it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WaterLevel {
+    /// Whether the tank needs refilling before the machine can heat up or brew
+    pub is_empty: bool,
+}
+
+/// Brew by weight settings of a machine paired with a scale, from the `CMBrewByWeight` widget
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrewByWeight {
+    /// Whether the scale is currently connected to the machine
+    pub scale_connected: bool,
+    /// Weight of espresso in grams at which the machine stops the shot
+    pub target_weight: Option<f64>,
+}
+
+/// A dose for one of a machine's buttons, as reported by the `CMGroupDoses` widget
+#[derive(Debug, Clone, Deserialize)]
+pub struct Dose {
+    /// The button, e.g. `DoseA`
+    #[serde(rename = "doseIndex")]
+    pub dose_index: String,
+    /// The size of the dose, in the units of the dosing mode (e.g. seconds for `TimeType`)
+    pub dose: f64,
+    #[serde(rename = "doseMin", default)]
+    pub dose_min: Option<f64>,
+    #[serde(rename = "doseMax", default)]
+    pub dose_max: Option<f64>,
+}
+
+/// How long a button on a machine which doses by time brews for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrewTime {
+    /// The button, e.g. `DoseA`
+    pub button: String,
+    /// How long the button brews for, in seconds
+    pub seconds: f64,
+    /// The shortest brew time the machine accepts for this button, in seconds
+    pub min_seconds: Option<f64>,
+    /// The longest brew time the machine accepts for this button, in seconds
+    pub max_seconds: Option<f64>,
+}
+
+/// Dosing settings of a machine, from the `CMGroupDoses` widget, with the brew time of
+/// each button if the machine doses by time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrewTimes {
+    /// How the machine measures doses, e.g. `TimeType`, or `PulsesType` for machines which
+    /// dose by volume
+    pub mode: Option<String>,
+    /// The brew time of each button. This is empty unless the machine doses by time.
+    pub buttons: Vec<BrewTime>,
+}
+
+impl BrewTimes {
+    /// The dosing mode of machines which dose by time
+    pub const TIME_MODE: &'static str = "TimeType";
+
+    /// Whether the machine doses by time, rather than by volume
+    pub fn is_time_based(&self) -> bool {
+        self.mode.as_deref() == Some(Self::TIME_MODE)
+    }
+
+    /// Find a button by its full name (e.g. `DoseA`) or just its letter (e.g. `A`),
+    /// ignoring case
+    pub fn button(&self, name: &str) -> Option<&BrewTime> {
+        self.buttons.iter().find(|brew_time| {
+            brew_time.button.eq_ignore_ascii_case(name)
+                || brew_time
+                    .button
+                    .strip_prefix("Dose")
+                    .is_some_and(|letter| letter.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+/// Temperatures of a machine's coffee boiler, from the `CMCoffeeBoiler` widget
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoilerTemperature {
+    /// The temperature the boiler heats to, in degrees Celsius
+    pub target: Option<f64>,
+    /// The boiler's current temperature in degrees Celsius, if the machine reports it
+    pub current: Option<f64>,
+}
+
+/// An optional feature which only some machines have, detected from the widgets on their
+/// dashboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Stopping the shot at a target weight, using a paired scale
+    BrewByWeight,
+    /// Reporting the water hardness and filter settings
+    WaterFilter,
+    /// Reporting the dose of each button, e.g. how long it brews for
+    Dosing,
+    /// Setting the target temperature of the coffee boiler
+    CoffeeBoiler,
+}
+
+impl Capability {
+    /// Every capability which can be detected
+    pub const ALL: &'static [Capability] = &[
+        Capability::BrewByWeight,
+        Capability::WaterFilter,
+        Capability::Dosing,
+        Capability::CoffeeBoiler,
+    ];
+
+    /// The dashboard widget which machines with this capability report
+    pub fn widget_code(self) -> &'static str {
+        match self {
+            Capability::BrewByWeight => "CMBrewByWeight",
+            Capability::WaterFilter => "CMWaterFilter",
+            Capability::Dosing => "CMGroupDoses",
+            Capability::CoffeeBoiler => "CMCoffeeBoiler",
+        }
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::BrewByWeight => write!(f, "brewing by weight"),
+            Capability::WaterFilter => write!(f, "water filter settings"),
+            Capability::Dosing => write!(f, "dosing settings"),
+            Capability::CoffeeBoiler => write!(f, "setting the coffee boiler temperature"),
+        }
+    }
+}
+
+/// A flat, structured view of a machine's status, suitable for scripting
+///
+/// The field names here are the ones accepted by `lm status --field`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MachineStatusSummary {
+    /// Serial number of the machine
+    pub serial: String,
+    /// Human-readable status, e.g. "On (Ready)" or "Standby"
+    pub status: String,
+    /// Whether the machine is switched on
+    pub is_on: bool,
+    /// Current mode of the machine, e.g. "BrewingMode" or "StandBy"
+    pub machine_mode: Option<String>,
+    /// Status of the coffee boiler, e.g. "Ready", "HeatingUp" or "NoWater"
+    pub coffee_boiler_status: Option<String>,
+    /// Target temperature of the coffee boiler in degrees Celsius
+    pub coffee_boiler_target_temp: Option<f64>,
+    /// When the coffee boiler is expected to be ready, in milliseconds since the Unix epoch
+    pub coffee_boiler_ready_at: Option<u64>,
+    /// Water hardness level configured on the machine
+    pub water_hardness: Option<u8>,
+    /// Type of water filter fitted
+    pub water_filter_type: Option<String>,
+    /// Remaining capacity of the water filter in litres
+    pub water_filter_remaining: Option<f64>,
+    /// Pump pressure in bar, only reported by some machines while brewing
+    pub pump_pressure: Option<f64>,
+}
+
+impl MachineStatusSummary {
+    /// Names of the fields that can be selected with `field`
+    pub const FIELDS: &'static [&'static str] = &[
+        "serial",
+        "status",
+        "is_on",
+        "machine_mode",
+        "coffee_boiler_status",
+        "coffee_boiler_target_temp",
+        "coffee_boiler_ready_at",
+        "water_hardness",
+        "water_filter_type",
+        "water_filter_remaining",
+        "pump_pressure",
+    ];
+
+    /// Get the value of a single field as a string. Missing values are returned as an empty string.
+    pub fn field(&self, name: &str) -> anyhow::Result<String> {
+        if !Self::FIELDS.contains(&name) {
+            return Err(anyhow::anyhow!(
+                "Unknown field '{}'. Available fields: {}",
+                name,
+                Self::FIELDS.join(", ")
+            ));
+        }
+
+        let value = serde_json::to_value(self)?;
+        Ok(match &value[name] {
+            serde_json::Value::Null => String::new(),
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+    }
+
+    /// CSV header row matching `to_csv_row`, with one column per field
+    pub fn csv_header() -> String {
+        Self::FIELDS.join(",")
+    }
+
+    /// Format the summary as a CSV row, with one column per field
+    pub fn to_csv_row(&self) -> String {
+        Self::FIELDS
+            .iter()
+            .map(|field| csv_escape(&self.field(field).unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl std::fmt::Display for MachineStatusSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.serial, self.status)
+    }
+}
+
+/// Quote a CSV value if it contains a separator, quote or line break
+pub fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
 }
 
 impl MachineStatus {
@@ -69,10 +709,69 @@ impl MachineStatus {
         false // Default to off if we can't determine the status
     }
 
+    /// Whether the machine is pulling a shot, according to the `CMMachineStatus` widget
+    pub fn is_brewing(&self) -> bool {
+        self.widget_output("CMMachineStatus")
+            .and_then(|output| output.status.as_deref())
+            == Some("Brewing")
+    }
+
+    /// What the machine is doing, e.g. heating up or ready to brew
+    ///
+    /// States this version doesn't recognise, including new coffee boiler states, are
+    /// [`MachineState::Unknown`] rather than being treated like a known state.
+    pub fn state(&self) -> MachineState {
+        let status = self
+            .widget_output("CMMachineStatus")
+            .and_then(|output| output.status.as_deref());
+        match status {
+            None => return MachineState::Unknown(None),
+            Some("StandBy") => return MachineState::Standby,
+            Some("Brewing") => return MachineState::Brewing,
+            Some("PoweredOn") => {}
+            Some(other) => return MachineState::Unknown(Some(other.to_string())),
+        }
+
+        match self.coffee_boiler_state() {
+            None => MachineState::On,
+            Some(BoilerState::Ready) => MachineState::Ready,
+            _ if self.water_level().is_some_and(|water| water.is_empty) => MachineState::NoWater,
+            Some(BoilerState::Unknown(status)) => MachineState::Unknown(Some(status)),
+            Some(BoilerState::Heating | BoilerState::NoWater | BoilerState::Off) => {
+                MachineState::Heating {
+                    ready_in: self.time_until_ready(None),
+                }
+            }
+        }
+    }
+
     pub fn get_status_string(&self) -> String {
         self.get_status_string_with_time(None)
     }
 
+    /// Get the status string, failing if the machine reports a state this crate doesn't
+    /// recognise
+    ///
+    /// [`MachineStatus::get_status_string`] is forgiving, showing "Unknown" or passing
+    /// unrecognised states through. This is the strict alternative, for monitoring which
+    /// should notice new or unexpected states. The error describes what wasn't recognised.
+    pub fn try_status(&self) -> Result<String, String> {
+        match self
+            .widget_output("CMMachineStatus")
+            .and_then(|output| output.status.as_deref())
+        {
+            None => return Err("The machine didn't report its status".to_string()),
+            Some("StandBy") | Some("PoweredOn") | Some("Brewing") => {}
+            Some(other) => return Err(format!("Unrecognised machine status '{}'", other)),
+        }
+
+        if let Some(BoilerState::Unknown(status)) = self.coffee_boiler_state() {
+            return Err(format!("Unrecognised coffee boiler status '{}'", status));
+        }
+
+        Ok(self.get_status_string())
+    }
+
     pub fn get_status_string_with_time(&self, current_time_ms: Option<u64>) -> String {
         // First, check if machine is powered on
         let mut is_powered_on = false;
@@ -98,52 +797,268 @@ impl MachineStatus {
         }
 
         // Machine is powered on, now check boiler status
-        for widget in &self.widgets {
-            if widget.code == "CMCoffeeBoiler" {
-                if let Some(output) = &widget.output {
-                    if let Some(status) = &output.status {
-                        if status == "Ready" {
-                            return "On (Ready)".to_string();
-                        } else if status == "NoWater" {
-                            return "On (No water)".to_string();
-                        } else if let Some(ready_time) = output.ready_start_time {
-                            let now = current_time_ms.unwrap_or_else(|| {
-                                SystemTime::now()
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_millis() as u64
-                            });
-
-                            if ready_time > now {
-                                let seconds_remaining = (ready_time - now) / 1000;
-                                let minutes_remaining = seconds_remaining / 60;
-
-                                if minutes_remaining == 0 {
-                                    return "On (Ready in < 1 min)".to_string();
-                                } else if minutes_remaining == 1 {
-                                    return "On (Ready in 1 min)".to_string();
-                                } else {
-                                    return format!("On (Ready in {} mins)", minutes_remaining);
-                                }
-                            } else {
-                                // Ready time is in the past, should be ready soon
-                                return "On (Ready in < 1 min)".to_string();
-                            }
-                        } else {
-                            // Heating but no ready time
-                            return "On (Ready soon)".to_string();
-                        }
-                    }
-                }
+        if let Some(boiler_state) = self.coffee_boiler_state() {
+            if boiler_state == BoilerState::Ready {
+                return "On (Ready)".to_string();
             }
+            if self.water_level().is_some_and(|water| water.is_empty) {
+                return "On (No water)".to_string();
+            }
+
+            let now = current_time_ms.and_then(|ms| DateTime::from_timestamp_millis(ms as i64));
+            return match self.time_until_ready(now) {
+                Some(remaining) => match remaining.as_secs() / 60 {
+                    // Includes a ready time in the past, when it should be ready soon
+                    0 => "On (Ready in < 1 min)".to_string(),
+                    1 => "On (Ready in 1 min)".to_string(),
+                    minutes_remaining => format!("On (Ready in {} mins)", minutes_remaining),
+                },
+                // Heating but no ready time
+                None => "On (Ready soon)".to_string(),
+            };
         }
 
         // Machine is on but we don't have boiler info
         "On".to_string()
     }
+
+    /// When the coffee boiler is expected to be ready to brew, in milliseconds since the Unix
+    /// epoch
+    ///
+    /// Returns `None` if the machine is off, the boiler is already ready, or the machine
+    /// doesn't say when it will be ready.
+    pub fn ready_at_ms(&self) -> Option<u64> {
+        if !self.is_on() || self.coffee_boiler_state() == Some(BoilerState::Ready) {
+            return None;
+        }
+
+        self.widget_output("CMCoffeeBoiler")
+            .and_then(|output| output.ready_start_time)
+    }
+
+    /// How long until the coffee boiler is ready to brew, as of `now` (by default, the
+    /// current time)
+    ///
+    /// Returns `None` if the machine is off, the boiler is already ready, or the machine
+    /// doesn't say when it will be ready. If the expected ready time has passed but the
+    /// boiler isn't ready yet, the duration is zero.
+    pub fn time_until_ready(&self, now: Option<DateTime<Utc>>) -> Option<Duration> {
+        let ready_start_time = self.ready_at_ms()?;
+        let ready_at = DateTime::from_timestamp_millis(ready_start_time as i64)?;
+        let now = now.unwrap_or_else(Utc::now);
+
+        Some((ready_at - now).to_std().unwrap_or(Duration::ZERO))
+    }
+
+    /// Find the output of the widget with the given code, if present
+    fn widget_output(&self, code: &str) -> Option<&WidgetOutput> {
+        self.widgets
+            .iter()
+            .find(|widget| widget.code == code)
+            .and_then(|widget| widget.output.as_ref())
+    }
+
+    /// Get the state of the coffee boiler, if the machine reports it
+    pub fn coffee_boiler_state(&self) -> Option<BoilerState> {
+        self.widget_output("CMCoffeeBoiler")
+            .and_then(|output| output.status.as_deref())
+            .map(BoilerState::from)
+    }
+
+    /// Get the target and current temperatures of the coffee boiler, if the machine reports
+    /// its coffee boiler
+    pub fn coffee_boiler_temperature(&self) -> Option<BoilerTemperature> {
+        self.widget_output("CMCoffeeBoiler")
+            .map(|output| BoilerTemperature {
+                target: output.target_temperature,
+                current: output.current_temperature,
+            })
+    }
+
+    /// Get the alarms currently active on the machine, from the `CMAlarms` widget
+    pub fn alarms(&self) -> Vec<Alarm> {
+        self.widget_output("CMAlarms")
+            .and_then(|output| output.alarms.clone())
+            .unwrap_or_default()
+    }
+
+    /// The optional features the machine supports, based on which widgets it reports
+    pub fn capabilities(&self) -> Vec<Capability> {
+        Capability::ALL
+            .iter()
+            .copied()
+            .filter(|capability| self.widget_output(capability.widget_code()).is_some())
+            .collect()
+    }
+
+    /// Get the brew by weight settings, if the machine supports pairing with a scale
+    pub fn brew_by_weight(&self) -> Option<BrewByWeight> {
+        self.widget_output("CMBrewByWeight")
+            .map(|output| BrewByWeight {
+                scale_connected: output.scale_connected.unwrap_or(false),
+                target_weight: output.target_weight,
+            })
+    }
+
+    /// Get the dosing settings of the machine, including how long each button brews for if
+    /// it doses by time, or `None` if the machine doesn't report them
+    pub fn brew_times(&self) -> Option<BrewTimes> {
+        self.widget_output("CMGroupDoses").map(|output| BrewTimes {
+            mode: output.mode.clone(),
+            buttons: output
+                .doses
+                .as_ref()
+                .and_then(|doses| doses.get(BrewTimes::TIME_MODE))
+                .map(|doses| {
+                    doses
+                        .iter()
+                        .map(|dose| BrewTime {
+                            button: dose.dose_index.clone(),
+                            seconds: dose.dose,
+                            min_seconds: dose.dose_min,
+                            max_seconds: dose.dose_max,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Get the pump pressure in bar, from the `CMBrewing` widget, if the machine reports it
+    ///
+    /// Machines which report pressure generally only do so mid-brew, so this is `None` the rest
+    /// of the time. Pressures in other widgets, like a steam boiler's, are ignored.
+    pub fn pump_pressure_bar(&self) -> Option<f64> {
+        self.widget_output("CMBrewing")
+            .and_then(|output| output.pressure)
+    }
+
+    /// Get the strength of the machine's Wi-Fi signal in dBm, from the `CMConnectivity`
+    /// widget, if the machine reports it
+    ///
+    /// Values closer to zero are stronger: around -50 dBm is excellent, and below -75 dBm
+    /// the machine is likely to drop its connection.
+    pub fn wifi_signal(&self) -> Option<i32> {
+        self.widget_output("CMConnectivity")
+            .and_then(|output| output.rssi)
+    }
+
+    /// Get the level of the water tank, if the machine reports it
+    ///
+    /// The tank counts as empty if the `CMNoWater` widget's alarm is raised, or the coffee
+    /// boiler has stopped because there's no water.
+    pub fn water_level(&self) -> Option<WaterLevel> {
+        let alarm = self
+            .widget_output("CMNoWater")
+            .map(|output| output.no_water_alarm.unwrap_or(false));
+        let boiler_empty = self
+            .coffee_boiler_state()
+            .map(|state| state == BoilerState::NoWater);
+        if alarm.is_none() && boiler_empty.is_none() {
+            return None;
+        }
+
+        Some(WaterLevel {
+            is_empty: alarm.unwrap_or(false) || boiler_empty.unwrap_or(false),
+        })
+    }
+
+    /// Get the water hardness and filter configuration, if the machine reports it
+    pub fn water_config(&self) -> Option<WaterConfig> {
+        self.widget_output("CMWaterFilter")
+            .map(|output| WaterConfig {
+                hardness_level: output.water_hardness,
+                filter_type: output.filter_type.clone(),
+                filter_remaining_capacity: output.filter_remaining_capacity,
+            })
+    }
+
+    /// Build a structured summary of this status for the machine with the given serial number
+    pub fn summary(&self, serial: &str) -> MachineStatusSummary {
+        let machine = self.widget_output("CMMachineStatus");
+        let boiler = self.widget_output("CMCoffeeBoiler");
+        let water = self.water_config();
+
+        MachineStatusSummary {
+            serial: serial.to_string(),
+            status: self.get_status_string(),
+            is_on: self.is_on(),
+            machine_mode: machine.and_then(|output| output.mode.clone()),
+            coffee_boiler_status: boiler.and_then(|output| output.status.clone()),
+            coffee_boiler_target_temp: boiler.and_then(|output| output.target_temperature),
+            coffee_boiler_ready_at: boiler.and_then(|output| output.ready_start_time),
+            water_hardness: water.as_ref().and_then(|water| water.hardness_level),
+            water_filter_type: water.as_ref().and_then(|water| water.filter_type.clone()),
+            water_filter_remaining: water.and_then(|water| water.filter_remaining_capacity),
+            pump_pressure: self.pump_pressure_bar(),
+        }
+    }
+}
+
+/// Shows the same human-readable status as [`MachineStatus::get_status_string`], e.g.
+/// "On (Ready)" or "Standby"
+impl std::fmt::Display for MachineStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_status_string())
+    }
+}
+
+impl PartialEq for Machine {
+    fn eq(&self, other: &Self) -> bool {
+        self.serial_number == other.serial_number
+    }
+}
+
+impl Eq for Machine {}
+
+impl Hash for Machine {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.serial_number.hash(state);
+    }
 }
 
 impl Machine {
+    /// Key for sorting machines by name (ignoring case), then serial number. Unnamed machines
+    /// sort first.
+    pub fn sort_key(&self) -> (String, &str) {
+        (
+            self.name.as_deref().unwrap_or_default().to_lowercase(),
+            &self.serial_number,
+        )
+    }
+
+    /// Sort machines by name, then serial number
+    pub fn sort(machines: &mut [Machine]) {
+        machines.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    }
+
+    /// The `type` of things which are espresso machines
+    pub const COFFEE_MACHINE_TYPE: &'static str = "CoffeeMachine";
+
+    /// Whether this thing is an espresso machine, rather than another kind of device like a
+    /// grinder. Things without a type are assumed to be espresso machines.
+    pub fn is_coffee_machine(&self) -> bool {
+        self.device_type
+            .as_deref()
+            .is_none_or(|device_type| device_type == Self::COFFEE_MACHINE_TYPE)
+    }
+
+    /// Check whether the machine's name or location contains `filter`, ignoring case
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        [&self.name, &self.location]
+            .into_iter()
+            .flatten()
+            .any(|value| value.to_lowercase().contains(&filter))
+    }
+
+    /// Check whether the machine's model contains `model`, ignoring case
+    pub fn matches_model(&self, model: &str) -> bool {
+        self.model
+            .as_ref()
+            .is_some_and(|value| value.to_lowercase().contains(&model.to_lowercase()))
+    }
+
     pub async fn get_status_display(&self, client: &crate::client::LaMarzoccoClient) -> String {
         if !self.connected {
             return "Unavailable".to_string();
@@ -157,6 +1072,27 @@ impl Machine {
 }
 
 impl MachineCommand {
+    /// Modes which can be sent with `CoffeeMachineChangeMode`
+    pub const SUPPORTED_MODES: &'static [&'static str] = &["BrewingMode", "StandBy", "EcoMode"];
+
+    /// Build a command to switch the machine into one of [`Self::SUPPORTED_MODES`], matched
+    /// ignoring case
+    pub fn change_mode(mode: &str) -> Result<Self, String> {
+        Self::SUPPORTED_MODES
+            .iter()
+            .find(|supported| supported.eq_ignore_ascii_case(mode.trim()))
+            .map(|supported| Self {
+                mode: supported.to_string(),
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Unknown mode '{}'. Supported modes: {}",
+                    mode,
+                    Self::SUPPORTED_MODES.join(", ")
+                )
+            })
+    }
+
     pub fn turn_on() -> Self {
         Self {
             mode: "BrewingMode".to_string(),
@@ -170,6 +1106,28 @@ impl MachineCommand {
     }
 }
 
+impl TemperatureCommand {
+    /// Coffee boiler target temperatures in degrees Celsius which can be set
+    pub const COFFEE_BOILER_RANGE: std::ops::RangeInclusive<f32> = 85.0..=96.0;
+
+    /// Build a command to set the coffee boiler's target temperature, which must be within
+    /// [`Self::COFFEE_BOILER_RANGE`]
+    pub fn coffee_boiler(celsius: f32) -> Result<Self, String> {
+        if !Self::COFFEE_BOILER_RANGE.contains(&celsius) {
+            return Err(format!(
+                "Coffee boiler temperature must be between {}°C and {}°C",
+                Self::COFFEE_BOILER_RANGE.start(),
+                Self::COFFEE_BOILER_RANGE.end()
+            ));
+        }
+
+        Ok(Self {
+            boiler_index: 1,
+            target_temperature: celsius,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,6 +1141,111 @@ mod tests {
         assert_eq!(off_command.mode, "StandBy");
     }
 
+    #[test]
+    fn test_machine_command_change_mode() {
+        assert_eq!(
+            MachineCommand::change_mode("EcoMode").unwrap().mode,
+            "EcoMode"
+        );
+        assert_eq!(
+            MachineCommand::change_mode(" brewingmode ").unwrap().mode,
+            "BrewingMode"
+        );
+
+        let error = MachineCommand::change_mode("TurboMode").unwrap_err();
+        assert!(error.contains("Supported modes: BrewingMode, StandBy, EcoMode"));
+    }
+
+    #[test]
+    fn test_temperature_command_coffee_boiler() {
+        let command = TemperatureCommand::coffee_boiler(93.5).unwrap();
+        assert_eq!(
+            serde_json::to_value(&command).unwrap(),
+            serde_json::json!({"boilerIndex": 1, "targetTemperature": 93.5})
+        );
+
+        assert!(TemperatureCommand::coffee_boiler(85.0).is_ok());
+        assert!(TemperatureCommand::coffee_boiler(96.0).is_ok());
+
+        let error = TemperatureCommand::coffee_boiler(120.0).unwrap_err();
+        assert_eq!(
+            error,
+            "Coffee boiler temperature must be between 85°C and 96°C"
+        );
+        assert!(TemperatureCommand::coffee_boiler(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn test_credentials_is_older_than() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut credentials = Credentials::new("access", "refresh", "test@example.com")
+            .with_token_obtained_at(now - chrono::Duration::hours(2));
+
+        assert!(credentials.is_older_than(Duration::from_secs(60 * 60), now));
+        assert!(!credentials.is_older_than(Duration::from_secs(3 * 60 * 60), now));
+
+        // Tokens of unknown age are treated as too old
+        credentials.token_obtained_at = None;
+        assert!(credentials.is_older_than(Duration::from_secs(3 * 60 * 60), now));
+    }
+
+    #[test]
+    fn test_command_job_from_response() {
+        let job = CommandJob::from_response(r#"{"id": "abc", "status": "Pending"}"#).unwrap();
+        assert_eq!(job.id, "abc");
+        assert_eq!(job.status.as_deref(), Some("Pending"));
+
+        let job = CommandJob::from_response(
+            r#"[{"id": "abc", "status": "Error", "errorCode": "MachineOffline"}]"#,
+        )
+        .unwrap();
+        assert_eq!(job.error_code.as_deref(), Some("MachineOffline"));
+
+        assert!(CommandJob::from_response("").is_none());
+    }
+
+    #[test]
+    fn test_try_status() {
+        let widget = |code: &str, status: &str| Widget {
+            code: code.to_string(),
+            output: Some(WidgetOutput {
+                status: Some(status.to_string()),
+                ..Default::default()
+            }),
+        };
+
+        let ready = MachineStatus {
+            widgets: vec![
+                widget("CMMachineStatus", "PoweredOn"),
+                widget("CMCoffeeBoiler", "Ready"),
+            ],
+        };
+        assert_eq!(ready.try_status(), Ok("On (Ready)".to_string()));
+
+        let new_machine_state = MachineStatus {
+            widgets: vec![widget("CMMachineStatus", "Cleaning")],
+        };
+        assert_eq!(
+            new_machine_state.try_status(),
+            Err("Unrecognised machine status 'Cleaning'".to_string())
+        );
+
+        let new_boiler_state = MachineStatus {
+            widgets: vec![
+                widget("CMMachineStatus", "PoweredOn"),
+                widget("CMCoffeeBoiler", "Descaling"),
+            ],
+        };
+        assert_eq!(
+            new_boiler_state.try_status(),
+            Err("Unrecognised coffee boiler status 'Descaling'".to_string())
+        );
+
+        assert!(MachineStatus { widgets: vec![] }.try_status().is_err());
+    }
+
     #[test]
     fn test_machine_command_json_serialization() {
         let on_command = MachineCommand::turn_on();
@@ -204,6 +1267,7 @@ mod tests {
                     status: Some("StandBy".to_string()),
                     mode: None,
                     ready_start_time: None,
+                    ..Default::default()
                 }),
             }],
         };
@@ -219,6 +1283,7 @@ mod tests {
                     status: Some("PoweredOn".to_string()),
                     mode: None,
                     ready_start_time: None,
+                    ..Default::default()
                 }),
             }],
         };
@@ -244,6 +1309,7 @@ mod tests {
                         status: Some("PoweredOn".to_string()),
                         mode: None,
                         ready_start_time: None, // This widget doesn't have ready time
+                        ..Default::default()
                     }),
                 },
                 Widget {
@@ -252,6 +1318,7 @@ mod tests {
                         status: Some("Heating".to_string()),
                         mode: None,
                         ready_start_time: Some(1748515947000), // Future timestamp
+                        ..Default::default()
                     }),
                 },
             ],
@@ -285,6 +1352,65 @@ mod tests {
         let warming_status_soon =
             status_warming.get_status_string_with_time(Some(thirty_seconds_before));
         assert_eq!(warming_status_soon, "On (Ready in < 1 min)");
+
+        // The same information is available as a duration
+        let at = |ms: i64| DateTime::from_timestamp_millis(ms);
+        assert_eq!(
+            status_warming.time_until_ready(at(fixed_current_time as i64)),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(
+            status_warming.time_until_ready(at(thirty_seconds_before as i64)),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            status_warming.time_until_ready(at(1748515947001)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(status_warming.ready_at_ms(), Some(1748515947000));
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let scale: MachineStatus =
+            serde_json::from_str(include_str!("../tests/fixtures/machine_status_scale.json"))
+                .unwrap();
+        assert_eq!(scale.capabilities(), vec![Capability::BrewByWeight]);
+
+        let water_filter: MachineStatus = serde_json::from_str(include_str!(
+            "../tests/fixtures/machine_status_water_filter.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            water_filter.capabilities(),
+            vec![Capability::WaterFilter, Capability::CoffeeBoiler]
+        );
+
+        let basic: MachineStatus =
+            serde_json::from_str(include_str!("../tests/fixtures/machine_status_on.json")).unwrap();
+        assert_eq!(basic.capabilities(), vec![Capability::CoffeeBoiler]);
+
+        let no_widgets: MachineStatus = serde_json::from_str(include_str!(
+            "../tests/fixtures/machine_status_no_widget.json"
+        ))
+        .unwrap();
+        assert!(no_widgets.capabilities().is_empty());
+    }
+
+    #[test]
+    fn test_time_until_ready_when_not_warming() {
+        let ready: MachineStatus =
+            serde_json::from_str(include_str!("../tests/fixtures/machine_status_ready.json"))
+                .unwrap();
+        assert_eq!(ready.time_until_ready(None), None);
+
+        let standby: MachineStatus = serde_json::from_str(include_str!(
+            "../tests/fixtures/machine_status_standby.json"
+        ))
+        .unwrap();
+        assert_eq!(standby.time_until_ready(None), None);
+        assert_eq!(standby.ready_at_ms(), None);
+        assert_eq!(ready.ready_at_ms(), None);
     }
 
     #[test]
@@ -302,6 +1428,7 @@ mod tests {
                     status: Some("PoweredOn".to_string()),
                     mode: None,
                     ready_start_time: None,
+                    ..Default::default()
                 }),
             }],
         };
@@ -326,6 +1453,7 @@ mod tests {
                     status: None,
                     mode: Some("SomeMode".to_string()),
                     ready_start_time: None,
+                    ..Default::default()
                 }),
             }],
         };
@@ -344,6 +1472,7 @@ mod tests {
                         status: Some("PoweredOn".to_string()),
                         mode: None,
                         ready_start_time: None,
+                        ..Default::default()
                     }),
                 },
                 Widget {
@@ -352,6 +1481,7 @@ mod tests {
                         status: Some("NoWater".to_string()),
                         mode: None,
                         ready_start_time: None, // null when no water
+                        ..Default::default()
                     }),
                 },
             ],
@@ -361,6 +1491,490 @@ mod tests {
         assert_eq!(status_no_water.get_status_string(), "On (No water)");
     }
 
+    #[test]
+    fn test_machine_status_summary_fields() {
+        let status = MachineStatus {
+            widgets: vec![
+                Widget {
+                    code: "CMMachineStatus".to_string(),
+                    output: Some(WidgetOutput {
+                        status: Some("PoweredOn".to_string()),
+                        mode: Some("BrewingMode".to_string()),
+                        ..Default::default()
+                    }),
+                },
+                Widget {
+                    code: "CMCoffeeBoiler".to_string(),
+                    output: Some(WidgetOutput {
+                        status: Some("Ready".to_string()),
+                        target_temperature: Some(94.0),
+                        ..Default::default()
+                    }),
+                },
+            ],
+        };
+
+        assert_eq!(status.to_string(), "On (Ready)");
+
+        let summary = status.summary("MR033274");
+        assert_eq!(summary.to_string(), "MR033274: On (Ready)");
+        assert_eq!(summary.field("serial").unwrap(), "MR033274");
+        assert_eq!(summary.field("status").unwrap(), "On (Ready)");
+        assert_eq!(summary.field("is_on").unwrap(), "true");
+        assert_eq!(summary.field("machine_mode").unwrap(), "BrewingMode");
+        assert_eq!(summary.field("coffee_boiler_target_temp").unwrap(), "94.0");
+        assert_eq!(summary.field("coffee_boiler_ready_at").unwrap(), "");
+
+        // Every documented field can be selected
+        for field in MachineStatusSummary::FIELDS {
+            assert!(summary.field(field).is_ok());
+        }
+
+        let error = summary.field("nonsense").unwrap_err().to_string();
+        assert!(error.contains("Unknown field 'nonsense'"));
+        assert!(error.contains("coffee_boiler_target_temp"));
+    }
+
+    #[test]
+    fn test_machine_status_water_config() {
+        let status = MachineStatus {
+            widgets: vec![Widget {
+                code: "CMWaterFilter".to_string(),
+                output: Some(WidgetOutput {
+                    water_hardness: Some(3),
+                    filter_type: Some("BWTBestmax".to_string()),
+                    filter_remaining_capacity: Some(120.5),
+                    ..Default::default()
+                }),
+            }],
+        };
+
+        let water = status.water_config().unwrap();
+        assert_eq!(water.hardness_level, Some(3));
+        assert_eq!(water.filter_type, Some("BWTBestmax".to_string()));
+        assert_eq!(water.filter_remaining_capacity, Some(120.5));
+
+        let summary = status.summary("MR033274");
+        assert_eq!(summary.field("water_hardness").unwrap(), "3");
+        assert_eq!(summary.field("water_filter_remaining").unwrap(), "120.5");
+
+        // Machines which don't report water settings have no water config
+        assert!(MachineStatus { widgets: vec![] }.water_config().is_none());
+    }
+
+    #[test]
+    fn test_machine_status_wifi_signal() {
+        let status: MachineStatus = serde_json::from_str(
+            r#"{"widgets": [{"code": "CMConnectivity", "output": {"rssi": "-67"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(status.wifi_signal(), Some(-67));
+
+        // Machines which don't report their signal have none
+        assert!(MachineStatus { widgets: vec![] }.wifi_signal().is_none());
+    }
+
+    #[test]
+    fn test_machine_status_brew_times() {
+        let time_based: MachineStatus = serde_json::from_str(include_str!(
+            "../tests/fixtures/machine_status_brew_times.json"
+        ))
+        .unwrap();
+        let brew_times = time_based.brew_times().unwrap();
+        assert!(brew_times.is_time_based());
+        assert_eq!(brew_times.buttons.len(), 2);
+        assert_eq!(brew_times.button("b").unwrap().seconds, 32.5);
+        assert_eq!(brew_times.button("DoseA").unwrap().max_seconds, Some(60.0));
+        assert!(brew_times.button("C").is_none());
+        assert!(time_based.capabilities().contains(&Capability::Dosing));
+
+        let volumetric: MachineStatus = serde_json::from_str(
+            r#"{"widgets": [{"code": "CMGroupDoses", "output": {"mode": "PulsesType", "doses": {"PulsesType": [{"doseIndex": "DoseA", "dose": 126.0}]}}}]}"#,
+        )
+        .unwrap();
+        let brew_times = volumetric.brew_times().unwrap();
+        assert!(!brew_times.is_time_based());
+        assert!(brew_times.buttons.is_empty());
+
+        assert!(MachineStatus { widgets: vec![] }.brew_times().is_none());
+    }
+
+    #[test]
+    fn test_machine_state() {
+        let status = |widgets: &str| -> MachineStatus {
+            serde_json::from_str(&format!(r#"{{"widgets": [{}]}}"#, widgets)).unwrap()
+        };
+        let machine = |status: &str| {
+            format!(
+                r#"{{"code": "CMMachineStatus", "output": {{"status": "{}"}}}}"#,
+                status
+            )
+        };
+        let boiler = |status: &str| {
+            format!(
+                r#"{{"code": "CMCoffeeBoiler", "output": {{"status": "{}"}}}}"#,
+                status
+            )
+        };
+        let on = machine("PoweredOn");
+
+        assert_eq!(status(&machine("StandBy")).state(), MachineState::Standby);
+        assert_eq!(status(&machine("Brewing")).state(), MachineState::Brewing);
+        assert_eq!(
+            status(&format!("{},{}", on, boiler("Ready"))).state(),
+            MachineState::Ready
+        );
+        assert_eq!(
+            status(&format!("{},{}", on, boiler("HeatingUp"))).state(),
+            MachineState::Heating { ready_in: None }
+        );
+        assert_eq!(
+            status(&format!("{},{}", on, boiler("NoWater"))).state(),
+            MachineState::NoWater
+        );
+        assert_eq!(status(&on).state(), MachineState::On);
+
+        // New states aren't mistaken for known ones
+        assert_eq!(
+            status(&machine("Cleaning")).state(),
+            MachineState::Unknown(Some("Cleaning".to_string()))
+        );
+        assert_eq!(
+            status(&format!("{},{}", on, boiler("Descaling"))).state(),
+            MachineState::Unknown(Some("Descaling".to_string()))
+        );
+        assert_eq!(
+            MachineStatus { widgets: vec![] }.state(),
+            MachineState::Unknown(None)
+        );
+    }
+
+    #[test]
+    fn test_machine_state_display() {
+        let heating = |seconds: u64| MachineState::Heating {
+            ready_in: Some(Duration::from_secs(seconds)),
+        };
+        assert_eq!(heating(30).to_string(), "On (Ready in < 1 min)");
+        assert_eq!(heating(90).to_string(), "On (Ready in 1 min)");
+        assert_eq!(heating(300).to_string(), "On (Ready in 5 mins)");
+        assert_eq!(
+            MachineState::Heating { ready_in: None }.to_string(),
+            "On (Ready soon)"
+        );
+        assert_eq!(MachineState::Ready.to_string(), "On (Ready)");
+        assert_eq!(
+            MachineState::Unknown(Some("Cleaning".to_string())).to_string(),
+            "Unknown (Cleaning)"
+        );
+    }
+
+    #[test]
+    fn test_is_brewing() {
+        let status = |machine_status: &str| -> MachineStatus {
+            serde_json::from_str(&format!(
+                r#"{{"widgets": [{{"code": "CMMachineStatus", "output": {{"status": "{}"}}}}]}}"#,
+                machine_status
+            ))
+            .unwrap()
+        };
+
+        let brewing = status("Brewing");
+        assert!(brewing.is_brewing());
+        assert!(brewing.is_on());
+        assert_eq!(brewing.try_status().unwrap(), "Brewing");
+
+        assert!(!status("PoweredOn").is_brewing());
+        assert!(!MachineStatus { widgets: vec![] }.is_brewing());
+    }
+
+    #[test]
+    fn test_water_level() {
+        let status = |widgets: &str| -> MachineStatus {
+            serde_json::from_str(&format!(r#"{{"widgets": [{}]}}"#, widgets)).unwrap()
+        };
+        let machine_on = r#"{"code": "CMMachineStatus", "output": {"status": "PoweredOn"}}"#;
+        let no_water = |alarm: bool| {
+            format!(
+                r#"{{"code": "CMNoWater", "output": {{"allarm": {}}}}}"#,
+                alarm
+            )
+        };
+        let boiler = |state: &str| {
+            format!(
+                r#"{{"code": "CMCoffeeBoiler", "output": {{"status": "{}"}}}}"#,
+                state
+            )
+        };
+
+        assert_eq!(status(machine_on).water_level(), None);
+        assert_eq!(
+            status(&no_water(false)).water_level(),
+            Some(WaterLevel { is_empty: false })
+        );
+        assert_eq!(
+            status(&no_water(true)).water_level(),
+            Some(WaterLevel { is_empty: true })
+        );
+        assert_eq!(
+            status(&boiler("NoWater")).water_level(),
+            Some(WaterLevel { is_empty: true })
+        );
+
+        // The alarm alone is enough for the status to say the machine has no water
+        let alarm_while_heating = status(&format!(
+            "{}, {}, {}",
+            machine_on,
+            no_water(true),
+            boiler("HeatingUp")
+        ));
+        assert_eq!(alarm_while_heating.get_status_string(), "On (No water)");
+    }
+
+    #[test]
+    fn test_coffee_boiler_temperature() {
+        let status: MachineStatus = serde_json::from_str(
+            r#"{"widgets": [{"code": "CMCoffeeBoiler", "output": {"status": "HeatingUp", "targetTemperature": 94.0, "currentTemperature": "88.5"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status.coffee_boiler_temperature(),
+            Some(BoilerTemperature {
+                target: Some(94.0),
+                current: Some(88.5)
+            })
+        );
+
+        assert!(MachineStatus { widgets: vec![] }
+            .coffee_boiler_temperature()
+            .is_none());
+    }
+
+    #[test]
+    fn test_machine_status_pump_pressure() {
+        let status: MachineStatus = serde_json::from_str(
+            r#"{"widgets": [
+                {"code": "CMMachineStatus", "output": {"status": "BrewingMode"}},
+                {"code": "CMSteamBoilerTemperature", "output": {"pressure": 1.2}},
+                {"code": "CMBrewing", "output": {"pressure": 8.7}}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(status.pump_pressure_bar(), Some(8.7));
+        assert_eq!(
+            status.summary("MR033274").field("pump_pressure").unwrap(),
+            "8.7"
+        );
+
+        // Outside of a brew, there's no reading
+        assert!(MachineStatus { widgets: vec![] }
+            .pump_pressure_bar()
+            .is_none());
+    }
+
+    #[test]
+    fn test_coffee_boiler_state() {
+        let boiler = |status: &str| MachineStatus {
+            widgets: vec![Widget {
+                code: "CMCoffeeBoiler".to_string(),
+                output: Some(WidgetOutput {
+                    status: Some(status.to_string()),
+                    ..Default::default()
+                }),
+            }],
+        };
+
+        assert_eq!(
+            boiler("Ready").coffee_boiler_state(),
+            Some(BoilerState::Ready)
+        );
+        assert_eq!(
+            boiler("Heating").coffee_boiler_state(),
+            Some(BoilerState::Heating)
+        );
+        assert_eq!(
+            boiler("NoWater").coffee_boiler_state(),
+            Some(BoilerState::NoWater)
+        );
+        assert_eq!(
+            boiler("StandBy").coffee_boiler_state(),
+            Some(BoilerState::Off)
+        );
+        assert_eq!(
+            boiler("Descaling").coffee_boiler_state(),
+            Some(BoilerState::Unknown("Descaling".to_string()))
+        );
+        assert_eq!(
+            MachineStatus { widgets: vec![] }.coffee_boiler_state(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_machine_status_summary_csv() {
+        let status = MachineStatus {
+            widgets: vec![Widget {
+                code: "CMMachineStatus".to_string(),
+                output: Some(WidgetOutput {
+                    status: Some("StandBy".to_string()),
+                    mode: Some("StandBy".to_string()),
+                    ..Default::default()
+                }),
+            }],
+        };
+
+        let summary = status.summary("MR033274");
+        assert!(MachineStatusSummary::csv_header().starts_with("serial,status,is_on,"));
+        assert!(summary
+            .to_csv_row()
+            .starts_with("MR033274,Standby,false,StandBy,"));
+        assert_eq!(
+            summary.to_csv_row().split(',').count(),
+            MachineStatusSummary::FIELDS.len()
+        );
+
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("On, sort of"), "\"On, sort of\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_machine_status_alarms() {
+        let status = MachineStatus {
+            widgets: vec![Widget {
+                code: "CMAlarms".to_string(),
+                output: Some(WidgetOutput {
+                    alarms: Some(vec![Alarm {
+                        code: "CoffeeBoilerProbeError".to_string(),
+                        description: Some("Coffee boiler probe error".to_string()),
+                    }]),
+                    ..Default::default()
+                }),
+            }],
+        };
+
+        let alarms = status.alarms();
+        assert_eq!(alarms.len(), 1);
+        assert_eq!(alarms[0].code, "CoffeeBoilerProbeError");
+
+        // No alarms widget means no active alarms
+        assert!(MachineStatus { widgets: vec![] }.alarms().is_empty());
+    }
+
+    #[test]
+    fn test_machine_status_parsing_tolerates_type_drift() {
+        let json = r#"{
+            "widgets": [
+                "not a widget",
+                {"code": "CMMachineStatus", "output": {"status": "PoweredOn", "mode": 42}},
+                {"code": "CMCoffeeBoiler", "output": {
+                    "status": "Heating",
+                    "readyStartTime": "1748515947000",
+                    "targetTemperature": "93.5"
+                }},
+                {"code": "CMWaterFilter", "output": "unexpected"},
+                {"code": "CMAlarms"}
+            ]
+        }"#;
+
+        let status: MachineStatus = serde_json::from_str(json).unwrap();
+        assert_eq!(status.widgets.len(), 4);
+        assert!(status.is_on());
+        assert_eq!(
+            status.get_status_string_with_time(Some(1748515647000)),
+            "On (Ready in 5 mins)"
+        );
+
+        let summary = status.summary("MR033274");
+        assert_eq!(summary.machine_mode, None);
+        assert_eq!(summary.coffee_boiler_target_temp, Some(93.5));
+        assert!(status.water_config().is_none());
+        assert!(status.alarms().is_empty());
+
+        // A response without widgets is treated as unknown rather than an error
+        let status: MachineStatus = serde_json::from_str("{}").unwrap();
+        assert_eq!(status.get_status_string(), "Unknown");
+    }
+
+    /// Every value in `value`, replaced in turn with each of `replacements`
+    fn mutations(
+        value: &serde_json::Value,
+        replacements: &[serde_json::Value],
+    ) -> Vec<serde_json::Value> {
+        let mut results: Vec<serde_json::Value> = replacements.to_vec();
+
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, child) in map {
+                    for mutated_child in mutations(child, replacements) {
+                        let mut mutated = map.clone();
+                        mutated.insert(key.clone(), mutated_child);
+                        results.push(serde_json::Value::Object(mutated));
+                    }
+
+                    let mut without_key = map.clone();
+                    without_key.remove(key);
+                    results.push(serde_json::Value::Object(without_key));
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for (index, child) in items.iter().enumerate() {
+                    for mutated_child in mutations(child, replacements) {
+                        let mut mutated = items.clone();
+                        mutated[index] = mutated_child;
+                        results.push(serde_json::Value::Array(mutated));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        results
+    }
+
+    #[test]
+    fn test_machine_status_parsing_survives_mutated_fixtures() {
+        let replacements = [
+            serde_json::Value::Null,
+            serde_json::json!("text"),
+            serde_json::json!(-1),
+            serde_json::json!(1.5e300),
+            serde_json::json!(true),
+            serde_json::json!([]),
+            serde_json::json!({}),
+        ];
+
+        let fixtures = [
+            include_str!("../tests/fixtures/machine_status_on.json"),
+            include_str!("../tests/fixtures/machine_status_warming.json"),
+            include_str!("../tests/fixtures/machine_status_standby.json"),
+            include_str!("../tests/fixtures/machine_status_alarm.json"),
+            include_str!("../tests/fixtures/machine_status_water_filter.json"),
+        ];
+
+        for fixture in fixtures {
+            let value: serde_json::Value = serde_json::from_str(fixture).unwrap();
+
+            for mutated in mutations(&value, &replacements) {
+                // Any object should parse, however odd its contents
+                let parsed = serde_json::from_value::<MachineStatus>(mutated.clone());
+                if mutated.is_object() {
+                    assert!(parsed.is_ok(), "Failed to parse {}", mutated);
+                }
+
+                if let Ok(status) = parsed {
+                    let summary = status.summary("MR033274");
+                    for field in MachineStatusSummary::FIELDS {
+                        summary.field(field).unwrap();
+                    }
+                    summary.to_csv_row();
+                    status.coffee_boiler_state();
+                    status.water_config();
+                    status.alarms();
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_machine_properties() {
         let machine = Machine {
@@ -369,6 +1983,7 @@ mod tests {
             name: Some("Test Machine".to_string()),
             location: Some("Test Location".to_string()),
             connected: false,
+            device_type: Some("CoffeeMachine".to_string()),
         };
 
         // Test machine properties
@@ -378,4 +1993,150 @@ mod tests {
         assert_eq!(machine.location, Some("Test Location".to_string()));
         assert!(!machine.connected);
     }
+
+    #[test]
+    fn test_machine_equality_and_sorting() {
+        use std::collections::HashSet;
+
+        let machine = |serial: &str, name: Option<&str>| Machine {
+            serial_number: serial.to_string(),
+            model: None,
+            name: name.map(|name| name.to_string()),
+            location: None,
+            connected: true,
+            device_type: None,
+        };
+
+        // Machines with the same serial are equal, even if other fields differ
+        assert_eq!(
+            machine("A1", Some("Kitchen")),
+            machine("A1", Some("Office"))
+        );
+        assert_ne!(
+            machine("A1", Some("Kitchen")),
+            machine("B2", Some("Kitchen"))
+        );
+
+        let set: HashSet<Machine> = [
+            machine("A1", Some("Kitchen")),
+            machine("A1", Some("Kitchen (renamed)")),
+            machine("B2", None),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(set.len(), 2);
+
+        let mut machines = vec![
+            machine("C3", Some("office")),
+            machine("B2", Some("Kitchen")),
+            machine("A1", Some("Kitchen")),
+            machine("D4", None),
+        ];
+        Machine::sort(&mut machines);
+        let serials: Vec<&str> = machines.iter().map(|m| m.serial_number.as_str()).collect();
+        assert_eq!(serials, vec!["D4", "A1", "B2", "C3"]);
+    }
+
+    #[test]
+    fn test_machine_matches_filter() {
+        let machine = Machine {
+            serial_number: "TEST123".to_string(),
+            model: Some("Test Model".to_string()),
+            name: Some("Kitchen Machine".to_string()),
+            location: Some("Home".to_string()),
+            connected: true,
+            device_type: None,
+        };
+
+        assert!(machine.matches_filter("kitchen"));
+        assert!(machine.matches_filter("HOME"));
+        assert!(!machine.matches_filter("office"));
+        // Serial numbers and models aren't searched
+        assert!(!machine.matches_filter("TEST"));
+    }
+
+    #[test]
+    fn test_machine_matches_model() {
+        let machine = Machine {
+            serial_number: "LM123".to_string(),
+            model: Some("Linea Mini".to_string()),
+            name: None,
+            location: None,
+            connected: true,
+            device_type: None,
+        };
+
+        assert!(machine.matches_model("LINEA MINI"));
+        assert!(machine.matches_model("mini"));
+        assert!(!machine.matches_model("GS3"));
+
+        // Machines without a model never match
+        let unknown = Machine {
+            model: None,
+            ..machine
+        };
+        assert!(!unknown.matches_model("mini"));
+    }
+
+    #[test]
+    fn test_machine_summary_from_machine() {
+        let things: Vec<Machine> =
+            serde_json::from_str(include_str!("../tests/fixtures/things_with_grinder.json"))
+                .unwrap();
+
+        assert_eq!(
+            MachineSummary::from(&things[0]),
+            MachineSummary {
+                serial: "GS01234".to_string(),
+                name: Some("Test Machine".to_string()),
+                model: Some("GS3 AV".to_string()),
+                connected: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_grinder_from_thing() {
+        let things: Vec<Machine> =
+            serde_json::from_str(include_str!("../tests/fixtures/things_with_grinder.json"))
+                .unwrap();
+
+        assert_eq!(Grinder::from_thing(&things[0]), None);
+        let grinder = Grinder::from_thing(&things[1]).unwrap();
+        assert_eq!(grinder.serial_number, "G123456");
+        assert_eq!(grinder.model.as_deref(), Some("Pico"));
+        assert!(grinder.connected);
+    }
+
+    #[test]
+    fn test_grinder_status_string() {
+        let status: GrinderStatus = serde_json::from_str(
+            r#"{"widgets": [{"code": "GrinderStatus", "output": {"status": "Ready"}}, {"code": "GrinderDose", "output": {}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(status.status_string(), "Ready");
+
+        let empty: GrinderStatus = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty.status_string(), "Unknown");
+    }
+
+    #[test]
+    fn test_machine_is_coffee_machine() {
+        let things: Vec<Machine> =
+            serde_json::from_str(include_str!("../tests/fixtures/things_with_grinder.json"))
+                .unwrap();
+        let coffee_machines: Vec<&str> = things
+            .iter()
+            .filter(|thing| thing.is_coffee_machine())
+            .map(|thing| thing.serial_number.as_str())
+            .collect();
+        assert_eq!(coffee_machines, vec!["GS01234"]);
+
+        // Older responses without a type are assumed to be coffee machines
+        let untyped = Machine {
+            device_type: None,
+            ..things[0].clone()
+        };
+        assert!(untyped.is_coffee_machine());
+    }
 }